@@ -0,0 +1,237 @@
+use std::io::IsTerminal;
+
+pub const USAGE: &str = "\
+Usage: codebook2 [OPTIONS] [COMMAND]
+
+Commands:
+    repl                Starts the interactive calculator (default).
+    run <file> [args]   Runs a CSL script, passing it any further arguments.
+                        'f <file>' does the same.
+    eval <expr>         Evaluates an expression and prints the result.
+    -e <expr>           Same as eval.
+    -                   Evaluates standard input and prints the result.
+    check <file>        Checks a CSL script for syntax errors without running it.
+
+Options:
+    --rpn               Keeps the stack between REPL lines.
+    --session <path>    Starts the REPL from a saved session.
+    --config <path>     Loads a different startup file.
+    --no-config         Skips the startup file.
+    --stdlib            Loads the bundled std namespace (default).
+    --no-stdlib         Skips the bundled std namespace.
+    --color <when>      Colors output 'always', 'never' or 'auto' (default), which
+                        colors only a terminal and honors NO_COLOR.
+    --no-color          Same as --color=never.
+    --quiet             Hides the banner and optional output.
+    --version           Prints the version.
+    --help              Prints this message.";
+
+pub enum ColorChoice {
+    Always,
+    Never,
+    Auto,
+}
+
+impl ColorChoice {
+    fn parse(value: &str) -> Result<Self, String> {
+        match value {
+            "always" => Ok(ColorChoice::Always),
+            "never" => Ok(ColorChoice::Never),
+            "auto" => Ok(ColorChoice::Auto),
+            _ => Err(format!("'--color' must be 'always', 'never' or 'auto', not '{}'.", value)),
+        }
+    }
+
+    /// Resolves `auto`: colors are used only when stdout is a terminal and `NO_COLOR` is unset.
+    pub fn enabled(&self) -> bool {
+        match self {
+            ColorChoice::Always => true,
+            ColorChoice::Never => false,
+            ColorChoice::Auto => {
+                let no_color = std::env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty());
+                !no_color && std::io::stdout().is_terminal()
+            }
+        }
+    }
+}
+
+pub enum Command {
+    Repl,
+    Run(String),
+    Eval(String),
+    Stdin,
+    Check(String),
+    Version,
+    Help,
+}
+
+pub struct Options {
+    pub command: Command,
+    pub persistent: bool,
+    pub session: Option<String>,
+    pub config: Option<String>,
+    pub no_config: bool,
+    pub script_args: Vec<String>,
+    pub stdlib: bool,
+    pub color: ColorChoice,
+    pub quiet: bool,
+}
+
+impl Options {
+    pub fn new() -> Self {
+        Options {
+            command: Command::Repl,
+            persistent: false,
+            session: None,
+            config: None,
+            no_config: false,
+            script_args: Vec::new(),
+            stdlib: true,
+            color: ColorChoice::Auto,
+            quiet: false,
+        }
+    }
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn value(args: &[String], index: usize, name: &str) -> Result<String, String> {
+    match args.get(index) {
+        Some(value) => Ok(value.to_owned()),
+        None => Err(format!("'{}' requires an argument.", name)),
+    }
+}
+
+/// Parses the command-line arguments, not including the program name.
+pub fn parse(args: &[String]) -> Result<Options, String> {
+    let mut options = Options::new();
+    let mut command: Option<Command> = None;
+    let mut info: Option<Command> = None;
+    let mut index = 0;
+    while index < args.len() {
+        let arg = args[index].as_str();
+        match arg {
+
+            "--rpn" => options.persistent = true,
+
+            "--session" => {
+                index += 1;
+                options.session = Some(value(args, index, arg)?);
+            }
+
+            "--config" => {
+                index += 1;
+                options.config = Some(value(args, index, arg)?);
+            }
+
+            "--no-config" => options.no_config = true,
+
+            "--stdlib" => options.stdlib = true,
+
+            "--no-stdlib" => options.stdlib = false,
+
+            "--color" => {
+                index += 1;
+                options.color = ColorChoice::parse(&value(args, index, arg)?)?;
+            }
+
+            _ if arg.starts_with("--color=") => options.color = ColorChoice::parse(&arg["--color=".len()..])?,
+
+            "--no-color" => options.color = ColorChoice::Never,
+
+            "--quiet" | "-q" => options.quiet = true,
+
+            "--version" | "-V" => info = Some(Command::Version),
+
+            "--help" | "-h" => info = Some(Command::Help),
+
+            "repl" if command.is_none() => command = Some(Command::Repl),
+
+            "-" if command.is_none() => command = Some(Command::Stdin),
+
+            "run" | "f" | "eval" | "-e" | "check" if command.is_none() => {
+                index += 1;
+                let target = value(args, index, arg)?;
+                match arg {
+                    "eval" | "-e" => command = Some(Command::Eval(target)),
+                    "check" => command = Some(Command::Check(target)),
+                    _ => {
+                        // Everything after the script path belongs to the script.
+                        command = Some(Command::Run(target));
+                        options.script_args = args[index + 1..].to_vec();
+                        break;
+                    }
+                }
+            }
+
+            _ if arg.starts_with('-') => return Err(format!("Unrecognized option '{}'.", arg)),
+
+            _ => return Err(format!("Unexpected argument '{}'.", arg)),
+        }
+        index += 1;
+    }
+
+    if let Some(command) = info.or(command) {
+        options.command = command;
+    }
+    Ok(options)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn color_choice() {
+        assert!(ColorChoice::parse("always").unwrap().enabled());
+        assert!(!ColorChoice::parse("never").unwrap().enabled());
+        assert!(ColorChoice::parse("sometimes").is_err());
+    }
+
+    fn args(line: &str) -> Vec<String> {
+        line.split_whitespace().map(String::from).collect()
+    }
+
+    #[test]
+    fn subcommands() {
+        assert!(matches!(parse(&args("")).unwrap().command, Command::Repl));
+        assert!(matches!(parse(&args("repl --rpn")).unwrap().command, Command::Repl));
+        assert!(matches!(parse(&args("-")).unwrap().command, Command::Stdin));
+        assert!(matches!(parse(&args("eval 1")).unwrap().command, Command::Eval(expr) if expr == "1"));
+        assert!(matches!(parse(&args("-e 1")).unwrap().command, Command::Eval(expr) if expr == "1"));
+        assert!(matches!(parse(&args("check a.cb")).unwrap().command, Command::Check(path) if path == "a.cb"));
+        assert!(matches!(parse(&args("f a.cb")).unwrap().command, Command::Run(path) if path == "a.cb"));
+        assert!(matches!(parse(&args("-e 1 --help")).unwrap().command, Command::Help));
+        assert!(matches!(parse(&args("--version repl")).unwrap().command, Command::Version));
+        assert!(parse(&args("-e 1 check a.cb")).is_err());
+        assert!(parse(&args("stray")).is_err());
+    }
+
+    #[test]
+    fn color_option() {
+        assert!(matches!(parse(&args("--color=always")).unwrap().color, ColorChoice::Always));
+        assert!(matches!(parse(&args("--color never")).unwrap().color, ColorChoice::Never));
+        assert!(matches!(parse(&args("--color=always --no-color")).unwrap().color, ColorChoice::Never));
+        assert!(parse(&args("--color=sometimes")).is_err());
+    }
+
+    #[test]
+    fn missing_value() {
+        for line in ["--color", "--session", "--config", "run", "-e", "check"] {
+            assert_eq!(parse(&args(line)).err(), Some(format!("'{}' requires an argument.", line)));
+        }
+    }
+
+    #[test]
+    fn run_passes_on_trailing_args() {
+        let options = parse(&args("--quiet run a.cb --rpn -e 1 x")).unwrap();
+        assert!(matches!(options.command, Command::Run(path) if path == "a.cb"));
+        assert_eq!(options.script_args, args("--rpn -e 1 x"));
+        assert!(options.quiet);
+        assert!(!options.persistent);
+    }
+}
@@ -1,7 +1,14 @@
 pub mod session {
 
     use std::collections::HashMap;
-    use super::namespaces::{Namespace};
+    use super::namespaces::{all_namespaces, Namespace};
+    use super::history::{History, Snapshot};
+    use std::path::PathBuf;
+    use super::modules::Modules;
+    use super::natives::NativeFunction;
+    use super::output::Output;
+    use super::builtins::{builtin_constant, check_builtin};
+    use super::math::{AngleMode, Base};
     pub struct SessionInfo {
         pub functions: HashMap<String, Vec<String>>,
         pub variables: HashMap<String, f32>,
@@ -13,6 +20,30 @@ pub mod session {
         pub last: f32,
         pub namespaces: Vec<Namespace>,
         pub branches: HashMap<usize, usize>,
+        pub persistent: bool,
+        pub panel: bool,
+        pub quiet: bool,
+        pub history: History,
+        pub args: Vec<String>,
+        pub docs: HashMap<String, String>,
+        pub pending_doc: Option<String>,
+        /// The first error raised since it was last cleared.
+        pub error: Option<String>,
+        /// Set by `exit`; stops interpretation until it is cleared.
+        pub exit_code: Option<i32>,
+        pub natives: HashMap<String, NativeFunction>,
+        pub output: Output,
+        /// The directory of the running script, searched first by `import`.
+        pub script_dir: Option<PathBuf>,
+        pub modules: Modules,
+        /// Namespaces whose functions can be called without qualifying them.
+        pub opened: Vec<String>,
+        pub aliases: HashMap<String, String>,
+        /// The namespace of each function call being run, innermost last.
+        pub scopes: Vec<Option<String>>,
+        pub angle: AngleMode,
+        /// The base results are shown in by the REPL.
+        pub base: Base,
     }
     
     impl SessionInfo {
@@ -28,56 +59,557 @@ pub mod session {
                 last: 0.0,
                 namespaces: Vec::new(),
                 branches: HashMap::new(),
+                persistent: false,
+                panel: false,
+                quiet: false,
+                history: History::new(),
+                args: Vec::new(),
+                docs: HashMap::new(),
+                pending_doc: None,
+                error: None,
+                exit_code: None,
+                natives: HashMap::new(),
+                output: Output::default(),
+                script_dir: None,
+                modules: Modules::default(),
+                opened: Vec::new(),
+                aliases: HashMap::new(),
+                scopes: Vec::new(),
+                angle: AngleMode::default(),
+                base: Base::default(),
             }
         }
     
+        /// Clears functions and variables. Constants are kept.
         pub fn reset(&mut self) {
             self.functions.clear();
             self.variables.clear();
+            self.docs.clear();
         }
 
-        pub fn contains_function(&self, name: String) -> bool {
-            if self.functions.contains_key(&name) {
-                true
-            } else {
+        pub fn snapshot(&self) -> Snapshot {
+            Snapshot {
+                stack: self.stack.clone(),
+                variables: self.variables.clone(),
+                functions: self.functions.clone(),
+                last: self.last,
+            }
+        }
+
+        pub fn restore(&mut self, snapshot: Snapshot) {
+            self.stack = snapshot.stack;
+            self.variables = snapshot.variables;
+            self.functions = snapshot.functions;
+            self.last = snapshot.last;
+        }
+
+        /// Remembers the current state before a REPL entry is interpreted.
+        pub fn checkpoint(&mut self) {
+            let snapshot = self.snapshot();
+            self.history.checkpoint(snapshot);
+        }
+
+        /// Records the checkpoint as an undo step if the entry changed anything.
+        pub fn commit(&mut self) {
+            let current = self.snapshot();
+            self.history.commit(current);
+        }
+
+        /// The value of a constant of the session or a built-in one.
+        pub fn constant(&self, name: &str) -> Option<f32> {
+            self.constants.get(name).copied()
+                .or_else(|| builtin_constant(name).map(|constant| constant.value))
+        }
+
+        /// Explains why `name` cannot be bound by `def`, `const` or `fn`, if it is a built-in
+        /// word, which would be found first, or a constant, including one of an opened namespace.
+        pub fn check_binding(&self, name: &str) -> Result<(), String> {
+            check_builtin(name)?;
+            if self.constants.contains_key(name) {
+                return Err(format!("'{}' is a constant and cannot be redefined.", name));
+            }
+            if let Some(title) = self.opened_constant(name) {
+                return Err(format!("'{}' is a constant of '{}' and cannot be redefined.", name, title));
+            }
+            Ok(())
+        }
+
+        /// The title of the opened namespace that declares `name` as a constant, if any.
+        pub fn opened_constant(&self, name: &str) -> Option<&str> {
+            self.opened.iter()
+                .find(|title| self.namespace(title).is_some_and(|namespace| namespace.constants.contains_key(name)))
+                .map(String::as_str)
+        }
+
+        /// Finds a namespace by title, alias or `parent::child` path. Inside a namespace
+        /// function, its own children are found first.
+        pub fn namespace(&self, name: &str) -> Option<&Namespace> {
+            self.resolve_namespace(name).map(|(_, namespace)| namespace)
+        }
 
-            for namespace in &self.namespaces {
-                if namespace.functions.contains_key(&name) {
-                    return true;
+        /// Like [`SessionInfo::namespace`], also returning the namespace's full path.
+        pub fn resolve_namespace(&self, name: &str) -> Option<(String, &Namespace)> {
+            if let Some(scope) = self.scopes.last().and_then(|scope| scope.as_ref()) {
+                if let Some(found) = self.find_namespace(&format!("{}::{}", scope, name)) {
+                    return Some(found);
                 }
             }
+            self.find_namespace(name)
+        }
+
+        fn find_namespace(&self, path: &str) -> Option<(String, &Namespace)> {
+            let (head, tail) = match path.split_once("::") {
+                Some((head, tail)) => (head, Some(tail)),
+                None => (path, None),
+            };
+            let mut full = self.aliases.get(head).map(String::as_str).unwrap_or(head).to_owned();
+            if let Some(tail) = tail {
+                full = format!("{}::{}", full, tail);
+            }
 
-                false
+            let mut segments = full.split("::");
+            let first = segments.next()?;
+            let mut namespace = self.namespaces.iter().find(|namespace| namespace.title == first)?;
+            for segment in segments {
+                namespace = namespace.child(segment)?;
             }
+            Some((full, namespace))
         }
 
-        pub fn get_function(&self, name: String) -> Vec<String> {
-            if self.functions.contains_key(&name) {
-                self.functions.get(&name).unwrap().to_vec()
-            } else {
-                for namespace in &self.namespaces {
-                    if namespace.functions.contains_key(&name) {
-                        return namespace.functions.get(&name).unwrap().to_vec();
+        /// Every namespace, children included, with its full path.
+        pub fn all_namespaces(&self) -> Vec<(String, &Namespace)> {
+            all_namespaces(&self.namespaces)
+        }
+
+        /// Adds a namespace, replacing any earlier one with the same title. Host functions
+        /// of the earlier namespace are kept.
+        pub fn add_namespace(&mut self, mut namespace: Namespace) {
+            if let Some(index) = self.namespaces.iter().position(|existing| existing.title == namespace.title) {
+                let old = self.namespaces.remove(index);
+                for (name, native) in old.natives {
+                    namespace.natives.entry(name).or_insert(native);
+                }
+            }
+            self.namespaces.push(namespace);
+        }
+
+        pub fn open_namespace(&mut self, title: &str) {
+            if !self.opened.iter().any(|opened| opened == title) {
+                self.opened.push(title.to_owned());
+            }
+        }
+
+        /// Resolves a name to a function or a namespace value. `ns::name` looks only in that
+        /// namespace. Otherwise the session's own functions come first, then the namespace of the
+        /// function being run and its parents, then the namespaces opened with `use`; a name found
+        /// in two of those is ambiguous.
+        pub fn lookup_word(&self, name: &str) -> Result<Option<Word>, String> {
+            if name.starts_with('"') {
+                return Ok(None);
+            }
+
+            if let Some((prefix, word)) = name.rsplit_once("::") {
+                let (path, namespace) = self.resolve_namespace(prefix)
+                    .ok_or(format!("Unknown namespace '{}'.", prefix))?;
+                return namespace.word(word)
+                    .map(|found| Some(scoped(found, &path)))
+                    .ok_or(format!("'{}' is not defined in namespace '{}'.", word, path));
+            }
+
+            if let Some(body) = self.functions.get(name) {
+                return Ok(Some(Word::Function(body.to_vec(), None)));
+            }
+            if let Some(native) = self.natives.get(name) {
+                return Ok(Some(Word::Native(native.clone())));
+            }
+
+            let mut opened: Vec<&str> = self.opened.iter().map(String::as_str).collect();
+            if let Some(scope) = self.scopes.last().and_then(|scope| scope.as_ref()) {
+                let mut path = scope.as_str();
+                loop {
+                    if let Some((_, namespace)) = self.find_namespace(path) {
+                        if let Some(word) = namespace.word(name) {
+                            return Ok(Some(scoped(word, path)));
+                        }
+                        opened.extend(namespace.uses.iter().map(String::as_str));
                     }
+                    match path.rsplit_once("::") {
+                        Some((parent, _)) => path = parent,
+                        None => break,
+                    }
+                }
+            }
+
+            let mut found: Vec<(String, &Namespace)> = Vec::new();
+            for title in opened {
+                if let Some((path, namespace)) = self.find_namespace(title) {
+                    if namespace.word(name).is_some() && !found.iter().any(|(other, _)| *other == path) {
+                        found.push((path, namespace));
+                    }
+                }
+            }
+
+            match found.as_slice() {
+                [] => Ok(None),
+                [(path, namespace)] => Ok(namespace.word(name).map(|word| scoped(word, path))),
+                _ => {
+                    let choices: Vec<String> = found.iter()
+                        .map(|(path, _)| format!("{}::{}", path, name))
+                        .collect();
+                    Err(format!("'{}' is ambiguous; use {}.", name, choices.join(" or ")))
                 }
-                let empty = Vec::new();
-                empty
             }
         }
     }
+
+    /// Gives a namespace function the full path of its namespace as its scope.
+    fn scoped(word: Word, path: &str) -> Word {
+        match word {
+            Word::Function(body, _) => Word::Function(body, Some(path.to_owned())),
+            other => other,
+        }
+    }
+
+    /// Something a name can refer to.
+    pub enum Word {
+        /// A CSL function body, and the namespace it belongs to.
+        Function(Vec<String>, Option<String>),
+        Native(NativeFunction),
+        /// A variable or constant of a namespace.
+        Value(f32),
+    }
+
+    impl Default for SessionInfo {
+        fn default() -> Self {
+            Self::new()
+        }
+    }
     
 }
 
+pub mod output {
+    use std::cell::RefCell;
+    use std::io::{stdout, Write};
+    use std::rc::Rc;
+    use super::interpet::{BLUE, DEFAULT, GREEN, RED, YELLOW};
+
+    /// Where the interpreter's output goes.
+    pub trait Sink {
+        fn write(&mut self, text: &str);
+
+        fn flush(&mut self) {}
+    }
+
+    pub struct StdoutSink;
+
+    impl Sink for StdoutSink {
+        fn write(&mut self, text: &str) {
+            print!("{}", text);
+        }
+
+        fn flush(&mut self) {
+            stdout().flush().expect("Could not flush console.");
+        }
+    }
+
+    pub struct StderrSink;
+
+    impl Sink for StderrSink {
+        fn write(&mut self, text: &str) {
+            eprint!("{}", text);
+        }
+    }
+
+    /// Collects output in memory. Clones share the same buffer, so keep one to read it back.
+    #[derive(Clone, Default)]
+    pub struct BufferSink {
+        buffer: Rc<RefCell<String>>,
+    }
+
+    impl BufferSink {
+        pub fn new() -> Self {
+            Self::default()
+        }
+
+        pub fn contents(&self) -> String {
+            self.buffer.borrow().clone()
+        }
+
+        /// Returns the output collected so far and empties the buffer.
+        pub fn take(&self) -> String {
+            self.buffer.take()
+        }
+    }
+
+    impl Sink for BufferSink {
+        fn write(&mut self, text: &str) {
+            self.buffer.borrow_mut().push_str(text);
+        }
+    }
+
+    /// Discards all output.
+    pub struct NullSink;
+
+    impl Sink for NullSink {
+        fn write(&mut self, _: &str) {}
+    }
+
+    const COLOR_NAMES: [&str; 8] = ["black", "red", "green", "yellow", "blue", "magenta", "cyan", "white"];
+
+    /// The ANSI code for a color name such as `red` or `bright_cyan`.
+    pub fn color_code(name: &str) -> Option<&'static str> {
+        const CODES: [&str; 8] = ["\x1b[30m", "\x1b[31m", "\x1b[32m", "\x1b[33m", "\x1b[34m", "\x1b[35m", "\x1b[36m", "\x1b[37m"];
+        const BRIGHT: [&str; 8] = ["\x1b[90m", "\x1b[91m", "\x1b[92m", "\x1b[93m", "\x1b[94m", "\x1b[95m", "\x1b[96m", "\x1b[97m"];
+
+        if name == "default" {
+            return Some(DEFAULT);
+        }
+        let (codes, name) = match name.strip_prefix("bright_") {
+            Some(name) => (BRIGHT, name),
+            None => (CODES, name),
+        };
+        COLOR_NAMES.iter().position(|color| *color == name).map(|index| codes[index])
+    }
+
+    /// The REPL prompt and the prefixes and colors of `out`, `warn` and `error` messages.
+    pub struct Theme {
+        pub prompt: String,
+        pub out_prefix: String,
+        pub warn_prefix: String,
+        pub error_prefix: String,
+        pub prompt_color: &'static str,
+        pub out_color: &'static str,
+        pub warn_color: &'static str,
+        pub error_color: &'static str,
+        pub prefixes: bool,
+    }
+
+    impl Theme {
+        pub fn new() -> Self {
+            Theme {
+                prompt: String::from("[In] << "),
+                out_prefix: String::from("[Out] >>"),
+                warn_prefix: String::from("[Warn]:"),
+                error_prefix: String::from("[Error]:"),
+                prompt_color: BLUE,
+                out_color: GREEN,
+                warn_color: YELLOW,
+                error_color: RED,
+                prefixes: true,
+            }
+        }
+
+        /// Changes one setting, as the `theme` keyword does. `default` restores every setting.
+        pub fn set(&mut self, key: &str, value: &str) -> Result<(), String> {
+            let color = || color_code(value).ok_or(format!("Unknown color '{}'.", value));
+            match key {
+                "prompt" => self.prompt = value.to_owned(),
+                "out_prefix" => self.out_prefix = value.to_owned(),
+                "warn_prefix" => self.warn_prefix = value.to_owned(),
+                "error_prefix" => self.error_prefix = value.to_owned(),
+                "prompt_color" => self.prompt_color = color()?,
+                "out_color" => self.out_color = color()?,
+                "warn_color" => self.warn_color = color()?,
+                "error_color" => self.error_color = color()?,
+                "prefixes" => match value {
+                    "on" | "1" => self.prefixes = true,
+                    "off" | "0" => self.prefixes = false,
+                    _ => return Err(format!("'prefixes' must be 'on' or 'off', not '{}'.", value)),
+                },
+                "default" => *self = Theme::new(),
+                _ => return Err(format!("Unknown theme setting '{}'.", key)),
+            }
+            Ok(())
+        }
+    }
+
+    impl Default for Theme {
+        fn default() -> Self {
+            Self::new()
+        }
+    }
+
+    /// A sink along with whether ANSI colors are written to it and how messages look.
+    pub struct Output {
+        pub sink: Box<dyn Sink>,
+        /// Where error messages go instead of `sink`, if set.
+        pub errors: Option<Box<dyn Sink>>,
+        pub color: bool,
+        pub theme: Theme,
+    }
+
+    impl Output {
+        pub fn new(sink: Box<dyn Sink>, color: bool) -> Self {
+            Output { sink, errors: None, color, theme: Theme::new() }
+        }
+
+        pub fn stdout(color: bool) -> Self {
+            Self::new(Box::new(StdoutSink), color)
+        }
+
+        /// Returns `code` if colors are enabled, otherwise an empty string.
+        pub fn paint(&self, code: &'static str) -> &'static str {
+            if self.color {
+                code
+            } else {
+                ""
+            }
+        }
+
+        pub fn print(&mut self, text: &str) {
+            self.sink.write(text);
+        }
+
+        pub fn println(&mut self, text: &str) {
+            self.sink.write(text);
+            self.sink.write("\n");
+        }
+
+        pub fn flush(&mut self) {
+            self.sink.flush();
+        }
+
+        pub fn set_color(&mut self, colorcode: &'static str) {
+            let code = self.paint(colorcode);
+            self.print(code);
+            self.flush();
+        }
+
+        /// Prints a message in `color`, after `prefix` if the theme shows prefixes.
+        fn message(&mut self, color: &'static str, prefix: &str, msg: &str) {
+            self.set_color(color);
+            if self.theme.prefixes {
+                let line = format!("{} {}", prefix, msg);
+                self.println(&line);
+            } else {
+                self.println(msg);
+            }
+        }
+
+        pub fn out(&mut self, msg: &str) {
+            let prefix = self.theme.out_prefix.clone();
+            self.message(self.theme.out_color, &prefix, msg);
+        }
+
+        pub fn warn(&mut self, msg: &str) {
+            let prefix = self.theme.warn_prefix.clone();
+            self.message(self.theme.warn_color, &prefix, msg);
+        }
+
+        pub fn error(&mut self, msg: &str) {
+            let prefix = self.theme.error_prefix.clone();
+            match self.errors.take() {
+                Some(errors) => {
+                    let sink = std::mem::replace(&mut self.sink, errors);
+                    self.message(self.theme.error_color, &prefix, msg);
+                    self.set_color(DEFAULT);
+                    self.errors = Some(std::mem::replace(&mut self.sink, sink));
+                }
+                None => self.message(self.theme.error_color, &prefix, msg),
+            }
+        }
+
+        pub fn prompt(&mut self) {
+            self.set_color(self.theme.prompt_color);
+            let prompt = self.theme.prompt.clone();
+            self.print(&prompt);
+            self.flush();
+        }
+    }
+
+    impl Default for Output {
+        fn default() -> Self {
+            Self::stdout(false)
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn buffer_clones_share_output() {
+            let buffer = BufferSink::new();
+            let mut output = Output::new(Box::new(buffer.clone()), false);
+            output.out("hello");
+            output.print("a");
+            assert_eq!(buffer.contents(), "[Out] >> hello\na");
+            assert_eq!(buffer.take(), "[Out] >> hello\na");
+            assert_eq!(buffer.contents(), "");
+        }
+
+        #[test]
+        fn interpreter_messages_reach_the_sink() {
+            let buffer = BufferSink::new();
+            let mut info = crate::interpreter::session::SessionInfo::new();
+            info.output = Output::new(Box::new(buffer.clone()), false);
+            crate::interpreter::interpet::interpret_line(String::from("{ hi } out \"x\" puts 1 +"), &mut info);
+            assert_eq!(buffer.take(), "[Out] >> hi\nx[Error]: Insufficient arguments!\n");
+        }
+
+        #[test]
+        fn colors_only_when_enabled() {
+            let buffer = BufferSink::new();
+            let mut output = Output::new(Box::new(buffer.clone()), false);
+            output.warn("careful");
+            assert_eq!(buffer.take(), "[Warn]: careful\n");
+
+            output.color = true;
+            output.warn("careful");
+            assert_eq!(buffer.take(), format!("{}[Warn]: careful\n", YELLOW));
+        }
+
+        #[test]
+        fn theme_settings() {
+            let mut theme = Theme::new();
+            theme.set("prompt", "> ").unwrap();
+            theme.set("error_color", "bright_magenta").unwrap();
+            theme.set("prefixes", "off").unwrap();
+            assert_eq!(theme.prompt, "> ");
+            assert_eq!(theme.error_color, "\x1b[95m");
+            assert!(!theme.prefixes);
+
+            assert_eq!(theme.set("out_color", "mauve"), Err(String::from("Unknown color 'mauve'.")));
+            assert!(theme.set("prefixes", "maybe").is_err());
+            assert_eq!(theme.set("font", "serif"), Err(String::from("Unknown theme setting 'font'.")));
+
+            theme.set("default", "").unwrap();
+            assert_eq!(theme.prompt, "[In] << ");
+            assert!(theme.prefixes);
+        }
+
+        #[test]
+        fn messages_follow_the_theme() {
+            let buffer = BufferSink::new();
+            let mut output = Output::new(Box::new(buffer.clone()), false);
+            output.theme.set("out_prefix", "=").unwrap();
+            output.out("1");
+            output.theme.set("prefixes", "off").unwrap();
+            output.error("bad");
+            assert_eq!(buffer.take(), "= 1\nbad\n");
+        }
+    }
+
+}
+
 pub mod interpet {
-use super::namespaces::Namespace;
-use super::session::SessionInfo;
+use super::session::{SessionInfo, Word};
+use super::math::{parse_number, Base};
+use super::builtins::{builtin_constant, lookup, Flow, Frame, BUILTINS, CATEGORIES, CONSTANTS};
+use super::natives::call_native;
+use super::storage::{escape_token, function_source};
+use std::env;
 use std::fs::File;
 use std::io;
-use std::io::stdout;
+use std::path::PathBuf;
 use std::io::Read;
-use std::io::Write;
-use std::process::exit;
+use super::output::Output;
+
 
+/// The `std` namespace, bundled into the binary so it is available from any directory.
+pub const STDLIB: &str = include_str!("std.cb");
+/// The title of the namespace `STDLIB` defines.
+pub const STDLIB_NAMESPACE: &str = "std";
 
 pub const GREEN: &str = "\x1b[32m";
 pub const RED: &str = "\x1b[31m";
@@ -88,15 +620,8 @@ pub const UNDERLINE: &str = "\x1b[4m";
 pub const NO_UNDERLINE: &str = "\x1b[24m";
 pub const DEFAULT: &str = "\x1b[0m";
 
-pub fn set_color(colorcode: &str) {
-    print!("{}", colorcode);
-    stdout().flush().expect("Could not change color.");
-}
-
-pub fn read_line() -> String {
-    set_color(BLUE);
-    print!("[In] << ");
-    stdout().flush().expect("Could not flush console.");
+pub fn read_line(output: &mut Output) -> String {
+    output.prompt();
 
     let mut input: String = String::from("");
     io::stdin()
@@ -106,16 +631,11 @@ pub fn read_line() -> String {
     input
 }
 
-pub fn out(msg: &str) {
-    set_color(GREEN);
-    println!("[Out] >> {}", msg);
-}
-
-pub fn get_string(line: &String, start: usize) -> (String, usize) {
+pub fn get_string(line: &str, start: usize) -> (String, usize) {
     let mut index = start;
     let chars: Vec<char> = line.chars().collect();
     let mut result = String::new();
-    while index < line.len() {
+    while index < chars.len() {
 
         let ch = chars[index];
 
@@ -123,7 +643,10 @@ pub fn get_string(line: &String, start: usize) -> (String, usize) {
 
             '\\' => {
                 index += 1;
-                match chars[index] {
+                let Some(&escaped) = chars.get(index) else {
+                    break;
+                };
+                match escaped {
 
                     'n' => result.push('\n'),
 
@@ -133,10 +656,8 @@ pub fn get_string(line: &String, start: usize) -> (String, usize) {
 
                     '\"' => result.push('\"'),
 
-                    _ => {
-                        error("Unknown escape sequence encountered.");
-                        exit(0);
-                    }
+                    // `check` reports unknown escapes; keep the character as written.
+                    other => result.push(other),
                 }
 
 
@@ -154,18 +675,21 @@ pub fn get_string(line: &String, start: usize) -> (String, usize) {
     (result, index)    
 }
 
-pub fn tokenize(line: &String) -> Vec<String> {
+pub fn tokenize(line: &str) -> Vec<String> {
     //println!("{line}");
     let mut tokens: Vec<String> = Vec::new();
     let mut index = 0;
     let line_chars: Vec<char> = line.chars().collect();
+    if line.starts_with("#!") {
+        index = line_chars.iter().position(|&ch| ch == '\n').unwrap_or(line_chars.len());
+    }
     let mut current_token = String::new();
-    while index < line.len() {
+    while index < line_chars.len() {
         let ch: char = line_chars[index];
 
         match ch {
 
-            ' ' => {
+            ' ' | '\t' | '\r' => {
                 if !current_token.is_empty() {
                     tokens.push(current_token.to_owned());
                     current_token.clear();
@@ -179,9 +703,35 @@ pub fn tokenize(line: &String) -> Vec<String> {
                 }
             }
 
+            '#' if current_token.is_empty() => {
+                let end = line_chars[index..].iter().position(|&ch| ch == '\n')
+                    .map_or(line_chars.len(), |offset| index + offset);
+                if line_chars.get(index + 1) == Some(&'\"') {
+                    // A doc comment that has already been tokenized once, e.g. inside {code}.
+                    let (doc, new_index) = get_string(line, index + 2);
+                    tokens.push(doc_token(&doc));
+                    index = new_index + 1;
+                } else {
+                    if line_chars.get(index + 1) == Some(&'#') {
+                        let doc: String = line_chars[index + 2..end].iter().collect();
+                        tokens.push(doc_token(doc.trim()));
+                    }
+                    index = end;
+                }
+            }
+
+            '/' if current_token.is_empty() && line_chars.get(index + 1) == Some(&'*') => {
+                index += 2;
+                while index < line_chars.len()
+                    && !(line_chars[index] == '*' && line_chars.get(index + 1) == Some(&'/')) {
+                    index += 1;
+                }
+                index += 1;
+            }
+
             '\"' => {
                 let mut res = String::from('\"');
-                let (str, new_index) = get_string(&line, index+1);
+                let (str, new_index) = get_string(line, index+1);
                 res.push_str(str.as_str());
                 res.push('\"');
                 
@@ -202,1045 +752,3204 @@ pub fn tokenize(line: &String) -> Vec<String> {
     tokens
 }
 
-pub fn error(msg: &str) {
-    set_color(RED);
-    println!("[Error]: {}", msg);
+/// Doc comments (`## text`) become a single `#"text"` token so they survive being
+/// recorded into {code} and tokenized again.
+pub fn doc_token(doc: &str) -> String {
+    format!("#{}", escape_token(&format!("\"{}\"", doc)))
+}
+
+/// Checks a script for unbalanced braces, unterminated strings and unknown escapes.
+/// Returns one message per problem, prefixed with its line number.
+pub fn check_source(source: &str) -> Vec<String> {
+    let mut problems = Vec::new();
+    let mut open_braces: Vec<usize> = Vec::new();
+    let mut current_token = String::new();
+    let mut line = 1;
+    let mut chars = source.chars().peekable();
+    if source.starts_with("#!") {
+        for ch in chars.by_ref() {
+            if ch == '\n' {
+                line += 1;
+                break;
+            }
+        }
+    }
+
+    let mut end_token = |token: &mut String, line: usize, problems: &mut Vec<String>| {
+        match token.as_str() {
+            "{" => open_braces.push(line),
+            "}" if open_braces.pop().is_none() => {
+                problems.push(format!("line {}: unexpected '}}'.", line));
+            }
+            _ => (),
+        }
+        token.clear();
+    };
+
+    while let Some(ch) = chars.next() {
+        match ch {
+            ' ' | '\t' | '\r' | '\n' => {
+                end_token(&mut current_token, line, &mut problems);
+                if ch == '\n' {
+                    line += 1;
+                }
+            }
+
+            '\"' => {
+                let start = line;
+                let mut terminated = false;
+                while let Some(ch) = chars.next() {
+                    match ch {
+                        '\\' => match chars.next() {
+                            Some('n') | Some('t') | Some('\\') | Some('\"') => (),
+                            Some(other) => problems.push(
+                                format!("line {}: unknown escape sequence '\\{}'.", line, other)),
+                            None => break,
+                        },
+                        '\"' => {
+                            terminated = true;
+                            break;
+                        }
+                        '\n' => line += 1,
+                        _ => (),
+                    }
+                }
+
+                if !terminated {
+                    problems.push(format!("line {}: unterminated string.", start));
+                }
+            }
+
+            '#' if current_token.is_empty() => {
+                for ch in chars.by_ref() {
+                    if ch == '\n' {
+                        line += 1;
+                        break;
+                    }
+                }
+            }
+
+            '/' if current_token.is_empty() && chars.peek() == Some(&'*') => {
+                let start = line;
+                let mut terminated = false;
+                let mut previous = chars.next();
+                for ch in chars.by_ref() {
+                    if ch == '\n' {
+                        line += 1;
+                    }
+                    if previous == Some('*') && ch == '/' {
+                        terminated = true;
+                        break;
+                    }
+                    previous = Some(ch);
+                }
+
+                if !terminated {
+                    problems.push(format!("line {}: unterminated block comment.", start));
+                }
+            }
+
+            _ => current_token.push(ch),
+        }
+    }
+    end_token(&mut current_token, line, &mut problems);
+
+    for opened in open_braces {
+        problems.push(format!("line {}: '{{' is never closed.", opened));
+    }
+    problems
 }
 
-pub fn warn(w: &str) {
-    set_color(YELLOW);
-    println!("[Warn]: {}", w);
+/// Prints an error and records it on the session, keeping the first one.
+pub fn fail(session: &mut SessionInfo, msg: &str) {
+    session.output.error(msg);
+    if session.error.is_none() {
+        session.error = Some(msg.to_owned());
+    }
 }
 
-pub fn interpret_vec(vector: &Vec<String>, session: &mut SessionInfo) {
+pub fn interpret_vec(vector: &[String], session: &mut SessionInfo) {
     let line = vec_to_line(vector);
     interpret_line(line, session);
 }
 
-pub fn vec_to_line(vector: &Vec<String>) -> String {
+pub fn vec_to_line(vector: &[String]) -> String {
     let mut res = String::from("");
-    let mut i = 0;
-    for item in vector {
-        if i < vector.len() && i > 0 {
-            res.push_str(" ");
+    for (i, item) in vector.iter().enumerate() {
+        if i > 0 {
+            res.push(' ');
         }
 
         res.push_str(item);
-        i += 1;
     }
 
     res
 }
 
-pub fn copy_vec(vector: &Vec<String>) -> Vec<String> {
+pub fn copy_vec(vector: &[String]) -> Vec<String> {
     let mut res: Vec<String> = Vec::new();
-    for item in vector.into_iter() {
+    for item in vector.iter() {
         res.push(item.to_string());
     }
 
     res
 }
 
-pub fn load_file(filepath: &str, session: &mut SessionInfo) -> Result<(), ()> {
+pub fn load_file(filepath: &str, session: &mut SessionInfo) -> io::Result<()> {
     let mut line = String::from("");
-    if let Ok(mut file) = File::open(filepath) {
-        file.read_to_string(&mut line)
-            .expect("Could not read file.");
-        interpret_line(line, session);
-        Ok(())
-    } else {
-        error(&format!("Could not read file '{}'.", filepath));
-        Err(())
+    match File::open(filepath) {
+        Ok(mut file) => {
+            file.read_to_string(&mut line)
+                .expect("Could not read file.");
+            interpret_line(line, session);
+            Ok(())
+        }
+        Err(e) => {
+            fail(session, &format!("Could not read file '{}'.", filepath));
+            Err(e)
+        }
     }
 }
 
-pub fn tab(num_tabs: u16) {
-    for _ in 0..num_tabs {
-        print!("\t");
-    }
-    stdout().flush().expect("failed to flush tabs.");
+/// Runs the bundled std library and opens its namespace, the only one opened without `use`.
+pub fn load_stdlib(session: &mut SessionInfo) {
+    interpret_line(STDLIB.to_owned(), session);
+    session.open_namespace(STDLIB_NAMESPACE);
 }
 
-pub fn print_help_item(keyword: &str, desc: &str) {
-    tab(2);
-    println!("{}{}{}{}{}{} => {}",
-                YELLOW, BOLD, UNDERLINE, keyword,
-                DEFAULT, GREEN, desc);
+/// `$XDG_CONFIG_HOME/codebook` (or `~/.config/codebook`).
+pub fn config_dir() -> Option<PathBuf> {
+    let config_dir = match env::var("XDG_CONFIG_HOME") {
+        Ok(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => PathBuf::from(env::var("HOME").ok()?).join(".config"),
+    };
+    Some(config_dir.join("codebook"))
 }
 
-pub fn print_help() {
-    set_color(GREEN);
-    out("\n");
-    tab(1);
-    println!("{}OPERATORS:{}{}", BOLD, DEFAULT, GREEN);
-    print_help_item("+", "(Addition) - adds the last two items on the stack.");
-    print_help_item("-", "(Subtraction) - subtracts the last two items on the stack.");
-    print_help_item("*", "(Multiplication) - multiplies the last two items on the stack.");
-    print_help_item("/", "(Division) - divides the last two items on the stack.");
-    print_help_item("**", "(Pow) - performs a to the power of b\n\t\twhere a and b are the last two items on the stack.");
-    print_help_item("%", "(Modulus) - Performs division and returns the remainder.");
-    print_help_item("++", "(Increment) - Increments the last item on the stack by 1.");
-    print_help_item("--", "(Decrement) - Decrements the last item on the stack by 1.");
-    print_help_item("n!", "(Factorial) - Returns the factorial of the last item on the stack.");
-    print_help_item("==", "(Equality) - Determines if the last two items on the stack are equal.");
-    print_help_item("!=", "(Not Equality) - Determines if the last two items on the stack are not equal.");
-    print_help_item(">", "(Greater Than) - Determines if the last item is less than the second to last.");
-    print_help_item("<", "(Less Than) - Determines if the last item is greater than the second to last");
-    print_help_item("<=", "(Less Than Equal To) - Determines if the last item is greater than or equal to the second to last.");
-    print_help_item(">=", "(Greater Than Equal To) - Determines if the last item is less than or equal to the second to last.");
-    print_help_item("<<", "(Shift Left) - Shifts the bits of a number to the left a specified amount of times.");
-    print_help_item(">>", "(Shift Right) - Shifts the bits of a number to the right a specified amount of times.");
-    print_help_item("&", "(AND) - Performs the logical AND operation on the last two items on the stack.");
-    print_help_item("|", "(OR) - Performs the logical OR operation on the last two items on the stack.");
-    print_help_item("^", "(XOR) - Performs the logical XOR operation on the last two items on the stack.");
-    println!("\t{}FUNCTIONS:{}{}", BOLD, DEFAULT, GREEN);
-    print_help_item("drop", "Deletes the last item on the stack.");
-    print_help_item("dup", "Duplicates the last item on the stack.");
-    print_help_item("STACK_SIZE", "Pushes the size of the stack onto the stack.");
-    println!("\t{}UTILITIES:{}{}", BOLD, DEFAULT, GREEN);
-    print_help_item("functions", "Prints a list of all user-defined functions.");
-    print_help_item("variables", "Prints a list of all user-defined variables.");
-    print_help_item("constants", "Prints a list of all user-defined constants.");
-    print_help_item("identifiers", "Prints a list of all user-defined identifiers.");
-    print_help_item("script", "Prints a list of tokens in {code}.");
-    print_help_item("pop_ident", "Pops the last identifier off the identifier stack.");
-    print_help_item("push_ident", "Pushes the last number onto the identifier stack.");
-    print_help_item("out", "Prints a desired message from [Out].");
-    print_help_item("oout", "Prints a suppressable desired message from [Out].");
-    print_help_item("warn", "Prints a desired message from [Warn].");
-    print_help_item("owarn", "Prints a suppressable desired message from [Warn].");
-    print_help_item("err", "Prints a desired message from [Error].");
-    print_help_item("oerr", "Prints a suppressable desired message from [Error].");
-    print_help_item("flush", "Prints a desired message w/o any source.");
-    print_help_item("def", "Defines a variable using the last identifier and number.");
-    print_help_item("fn", "Defines a function using the last {code} and identifier.");
-    print_help_item("{", "Begins adding tokens to code.");
-    print_help_item("}", "Ends adding tokens to innermost scope.");
-    print_help_item("load_file", "Loads a file into the calculator.");
-    print_help_item("reset", "If arguments are provided in {code}, it resets those specific variables/functions,
-    \t\totherwise, it resets all functions and variables.");
-    print_help_item("concatf", "Concatenates the top identifier to {code}.");
-    print_help_item("clear", "Clears the screen.");
-    print_help_item("ansi", "Executes ANSI commands within {code}.");
-    print_help_item("help", "Prints this page.");
-    print_help_item("credits", "Prints credits.");
-    print_help_item("about", "Prints more about this project.");
-    print_help_item("clear_code", "Clears {code}, this is automatically done after execution ends.");
-    print_help_item("quit", "Prompts the user to quit.");
-    println!("\t{}PROGRAMMING:{}{}", BOLD, DEFAULT, GREEN);
-    print_help_item("if", "If statement; executes {code} if last item on the stack is 1.");
-    print_help_item("elif", "Else if statement; executes {code} if last item on the stack is 1 and if statement is false.");
-    print_help_item("else", "Else statement; executes {code} if last if/elif is false.");
-    print_help_item("times", "Executes {code} specified amount of times.");
-    print_help_item("break", "Breaks execution.");
-    println!("\t\t{}MORE TO COME!{}{}", BOLD, DEFAULT, GREEN);
-    println!("\t{}FLAGS:{}{}", BOLD, DEFAULT, GREEN);
-    print_help_item("-s", "(Suppress output) - Suppresses any optional function output.");
-    print_help_item("-sall", "(Suppress all outputs) - Suppresses all optional function outputs.");    
+/// The user's startup file, `$XDG_CONFIG_HOME/codebook/config.cb` (or `~/.config/codebook/config.cb`).
+pub fn default_config_path() -> Option<PathBuf> {
+    Some(config_dir()?.join("config.cb"))
 }
 
-pub fn interpret_line(line: String, info: &mut SessionInfo) {
-    let tokens = tokenize(&line);    
+pub fn stack_line(stack: &[f32], base: Base) -> String {
+    let items: Vec<String> = stack.iter().map(|item| base.format(*item)).collect();
+    items.join(" ")
+}
 
-    let mut recording = false;
-    let mut suppress_out = false;
-    let mut supress_all = false;
-    let mut open_curly = 0;
-    for t in tokens {
-        let token = t.as_str();
-        if info.variables.contains_key(token) && !recording {
-            info.stack.push(*info.variables.get(token).unwrap());
-        } else if info.constants.contains_key(token) && !recording { 
-            info.stack.push(*info.constants.get(token).unwrap());
-        } else if token == "{" {
-            if open_curly > 0 {
-                open_curly += 1;
-                info.script.push(token.to_owned());
-                continue;
-            }
+pub fn print_levels<T: std::fmt::Display>(output: &mut Output, title: &str, items: &[T]) {
+    output.println(&format!("\t{}{}:{}{}", output.paint(BOLD), title, output.paint(DEFAULT), output.paint(GREEN)));
+    if items.is_empty() {
+        output.println("\t\t(empty)");
+        return;
+    }
 
-            recording = true;
-            open_curly += 1;
-            continue;
-        } else if token == "}" {
-            if open_curly > 1 {
-                open_curly -= 1;
-                info.script.push(token.to_owned());
-                continue;
-            } else if open_curly == 0 {
-                error("Unexpected '}' encountered!");
-                break;
-            }
+    for (i, item) in items.iter().enumerate() {
+        output.println(&format!("\t\t{}{}:{}{} {}",
+            output.paint(BOLD), items.len() - i, output.paint(DEFAULT), output.paint(GREEN), item));
+    }
+}
 
-            open_curly -= 1;
-            recording = false;
-        }
+pub fn print_panel(info: &mut SessionInfo) {
+    let output = &mut info.output;
+    output.out("\n");
+    print_levels(output, "STACK", &info.stack);
+    print_levels(output, "IDENTIFIERS", &info.idents);
+    output.println(&format!("\t{}{{CODE}}:{}{}", output.paint(BOLD), output.paint(DEFAULT), output.paint(GREEN)));
+    if info.script.is_empty() {
+        output.println("\t\t(empty)");
+    } else {
+        output.println(&format!("\t\t{{ {} }}", vec_to_line(&info.script)));
+    }
+}
 
-        if recording {
-            info.script.push(token.to_owned());
-            continue;
+pub fn print_doc(output: &mut Output, doc: Option<&String>) {
+    if let Some(doc) = doc {
+        for line in doc.lines() {
+            output.println(&format!("\t\t## {}", line));
         }
+    }
+}
 
-        match token {
+pub fn print_help_item(output: &mut Output, keyword: &str, desc: &str) {
+    output.println(&format!("\t\t{}{}{}{}{}{} => {}",
+                output.paint(YELLOW), output.paint(BOLD), output.paint(UNDERLINE), keyword,
+                output.paint(DEFAULT), output.paint(GREEN), desc));
+}
 
-            "else" => {
-                if let Some(res) = info.bool_val {
-                    if res == 0.0 {
-                        let exec = copy_vec(&info.script);
-                        info.script.clear();
-                        interpret_vec(&exec, info);
-                    } else {
-                        info.bool_val = None;
-                    }
-                } else {
-                    info.script.clear();
-                    continue;
+pub fn print_help(output: &mut Output) {
+    output.set_color(GREEN);
+    output.out("\n");
+    for category in CATEGORIES {
+        output.println(&format!("\t{}{}:{}{}", output.paint(BOLD), category, output.paint(DEFAULT), output.paint(GREEN)));
+        for entry in BUILTINS.iter().filter(|entry| entry.category == category) {
+            print_help_item(output, entry.name, entry.doc);
+        }
+    }
+    output.println(&format!("\t{}CONSTANTS:{}{}", output.paint(BOLD), output.paint(DEFAULT), output.paint(GREEN)));
+    for constant in CONSTANTS {
+        print_help_item(output, constant.name, constant.doc);
+    }
+    output.println(&format!("\t{}Type 'help <word>' for details on any keyword or function.{}{}",
+                output.paint(BOLD), output.paint(DEFAULT), output.paint(GREEN)));
+}
+
+/// Prints the help entry for a keyword, user function or namespace function.
+pub fn print_word_help(word: &str, info: &mut SessionInfo) -> bool {
+    if let Some(entry) = lookup(word) {
+        let output = &mut info.output;
+        output.out("\n");
+        output.println(&format!("\t{}{}{}{}  ( {} )",
+            output.paint(BOLD), entry.name, output.paint(DEFAULT), output.paint(GREEN), entry.effect));
+        output.println(&format!("\t\t{}", entry.doc));
+        output.println(&format!("\t\tCategory: {}", entry.category.to_lowercase()));
+        return true;
+    }
+
+    if let Some(constant) = builtin_constant(word) {
+        let output = &mut info.output;
+        output.out("\n");
+        output.println(&format!("\t{}{}{}{}  (built-in constant)",
+            output.paint(BOLD), constant.name, output.paint(DEFAULT), output.paint(GREEN)));
+        output.println(&format!("\t\t{}", constant.doc));
+        output.println(&format!("\t\t= {}", constant.value));
+        return true;
+    }
+
+    // `ns::name` only shows the entry from that namespace.
+    let (word, only) = match word.rsplit_once("::") {
+        Some((prefix, name)) => match info.resolve_namespace(prefix) {
+            Some((path, _)) => (name, Some(path)),
+            None => return false,
+        },
+        None => (word, None),
+    };
+
+    let mut sources: Vec<(String, Vec<String>, Option<String>)> = Vec::new();
+    let mut natives: Vec<(String, usize)> = Vec::new();
+    let mut values: Vec<(String, f32)> = Vec::new();
+    if only.is_none() {
+        if let Some(body) = info.functions.get(word) {
+            sources.push((String::from("user function"), body.to_vec(), info.docs.get(word).cloned()));
+        }
+        if let Some(native) = info.natives.get(word) {
+            natives.push((String::from("native function"), native.arity));
+        }
+    }
+    for (path, namespace) in info.all_namespaces() {
+        if only.as_ref().is_some_and(|only| *only != path) {
+            continue;
+        }
+        if let Some(body) = namespace.functions.get(word) {
+            sources.push((path.to_owned(), body.to_vec(), namespace.docs.get(word).cloned()));
+        }
+        if let Some(native) = namespace.natives.get(word) {
+            natives.push((path.to_owned(), native.arity));
+        }
+        if let Some(value) = namespace.variables.get(word).or_else(|| namespace.constants.get(word)) {
+            values.push((path, *value));
+        }
+    }
+
+    if sources.is_empty() && natives.is_empty() && values.is_empty() {
+        return false;
+    }
+
+    let output = &mut info.output;
+    output.out("\n");
+    for (source, value) in values {
+        output.println(&format!("\t{}{}{}{}  ({})", output.paint(BOLD), word, output.paint(DEFAULT), output.paint(GREEN), source));
+        output.println(&format!("\t\t= {}", value));
+    }
+    for (source, arity) in natives {
+        output.println(&format!("\t{}{}{}{}  ({})", output.paint(BOLD), word, output.paint(DEFAULT), output.paint(GREEN), source));
+        output.println(&format!("\t\tDefined by the host program; takes {} argument(s).", arity));
+    }
+    for (source, body, doc) in sources {
+        output.println(&format!("\t{}{}{}{}  ({})", output.paint(BOLD), word, output.paint(DEFAULT), output.paint(GREEN), source));
+        match doc {
+            Some(doc) => {
+                for line in doc.lines() {
+                    output.println(&format!("\t\t{}", line));
                 }
-                info.script.clear();
             }
+            None => output.println("\t\tNo documentation."),
+        }
+        output.println(&format!("\t\t{{ {} }}", function_source(&body)));
+    }
+    true
+}
+
+/// Whether `token` is the name that the next token defines, so it is not evaluated. This lets
+/// `{ 1 + } inc fn` replace an existing `inc` (or report that `mean` is built in), and
+/// `5 x def` an existing variable `x`, while `x 4 sqrt! def` still calls `sqrt!`.
+fn defines(token: &str, next: Option<&String>, info: &SessionInfo) -> bool {
+    let name = !matches!(token, "{" | "}")
+        && !token.starts_with('"')
+        && !token.contains("::")
+        && parse_number(token).is_none();
+    match next.map(String::as_str) {
+        Some("fn") => name,
+        Some("def" | "const") => name && (info.variables.contains_key(token)
+            || info.constant(token).is_some()
+            || info.opened_constant(token).is_some()),
+        _ => false,
+    }
+}
+
+pub fn interpret_line(line: String, info: &mut SessionInfo) {
+    let tokens = tokenize(&line);    
+
+    let mut recording = false;
+    let mut open_curly = 0;
+    let mut frame = Frame::new(tokens, info.quiet);
+    while let Some(t) = frame.tokens.next() {
+        if info.exit_code.is_some() {
+            break;
+        }
+
+        let token = t.as_str();
+        if !recording && defines(token, frame.tokens.as_slice().first(), info) {
+            info.idents.push(token.to_owned());
+            continue;
+        }
+        if info.variables.contains_key(token) && !recording {
+            info.stack.push(*info.variables.get(token).unwrap());
+        } else if let Some(value) = info.constant(token).filter(|_| !recording) {
+            // A constant is never the target of `def`, so unlike a variable it is not an identifier.
+            info.stack.push(value);
+            continue;
+        } else if token == "{" {
+            if open_curly > 0 {
+                open_curly += 1;
+                info.script.push(token.to_owned());
+                continue;
+            }
+
+            recording = true;
+            open_curly += 1;
+            continue;
+        } else if token == "}" {
+            if open_curly > 1 {
+                open_curly -= 1;
+                info.script.push(token.to_owned());
+                continue;
+            } else if open_curly == 0 {
+                fail(info, "Unexpected '}' encountered!");
+                break;
+            }
+
+            open_curly -= 1;
+            recording = false;
+        }
 
-            "elif" => {
-                if info.stack.len() == 0 {
-                    error("Insufficient arguments!");
+        if recording {
+            info.script.push(token.to_owned());
+            continue;
+        }
+
+        if token.starts_with("#\"") {
+            let (text, _) = get_string(token, 2);
+            frame.doc = Some(match frame.doc.take() {
+                Some(previous) => format!("{}\n{}", previous, text),
+                None => text,
+            });
+            continue;
+        }
+
+        if let Some(builtin) = lookup(token) {
+            if info.stack.len() < builtin.arity {
+                fail(info, "Insufficient arguments!");
+                break;
+            }
+
+            match (builtin.handler)(info, &mut frame) {
+                Ok(Flow::Next) => (),
+                Ok(Flow::Stop) => break,
+                Err(msg) => {
+                    fail(info, &msg);
                     break;
                 }
+            }
+            continue;
+        }
 
-                let cond = info.stack.pop().unwrap();
-                if let Some(res) = info.bool_val {
-                    if res == 0.0 {
-
-                        if cond == 1.0 {
-                            let exec = copy_vec(&info.script);
-                            info.script.clear();
-                            info.bool_val = Some(1.0);
-                            interpret_vec(&exec, info);
-                        } else {
-                            info.bool_val = Some(0.0);
-                        }
-                        
-                    } else {
-                        info.bool_val = None;
+        if let Some(num) = parse_number(token) {
+            info.stack.push(num);
+        } else {
+            match info.lookup_word(token) {
+                Ok(Some(Word::Function(body, scope))) => {
+                    info.scopes.push(scope);
+                    interpret_vec(&body, info);
+                    info.scopes.pop();
+                }
+                Ok(Some(Word::Native(native))) => {
+                    if let Err(msg) = call_native(&native, info) {
+                        fail(info, &msg);
+                        break;
                     }
-                } else {
-                    error("elif block missing if/elif block!");
-                    info.script.clear();
+                }
+                Ok(Some(Word::Value(value))) => {
+                    // Session variables were already pushed above and shadow namespace values.
+                    // Like them, a plain name can still be redefined with `def`, unless it is a constant.
+                    if !info.variables.contains_key(token) {
+                        info.stack.push(value);
+                    }
+                    if !token.contains("::") && info.opened_constant(token).is_none() {
+                        info.idents.push(token.to_owned());
+                    }
+                }
+                Ok(None) => info.idents.push(token.to_owned()),
+                Err(msg) => {
+                    fail(info, &msg);
                     break;
                 }
-                info.script.clear();
             }
+        }
+    }
+
+    // A doc comment on its own REPL line is kept for a definition on the next line.
+    if let Some(text) = frame.doc {
+        info.pending_doc = Some(match info.pending_doc.take() {
+            Some(previous) => format!("{}\n{}", previous, text),
+            None => text,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interpreter::test_session as session;
+
+    #[test]
+    fn tokenize_non_ascii_comment() {
+        assert_eq!(tokenize("5 # π is pi\n2 +"), vec!["5", "2", "+"]);
+        assert_eq!(tokenize("1 /* ∑ */ 2"), vec!["1", "2"]);
+    }
+
+    #[test]
+    fn tokenize_non_ascii_string() {
+        assert_eq!(tokenize("\"é\" puts"), vec!["\"é\"", "puts"]);
+        assert_eq!(tokenize("#!/usr/bin/env ✓\n\"ü\\n\""), vec!["\"ü\n\""]);
+    }
+
+    #[test]
+    fn tokenize_unterminated_escape() {
+        assert_eq!(tokenize("\"ä\\"), vec!["\"ä\""]);
+    }
+
+    #[test]
+    fn name_before_definition_is_not_evaluated() {
+        let mut info = session();
+        interpret_line(String::from("-sall { 1 + } inc fn x 1 def { 2 + } inc fn 5 x def"), &mut info);
+        assert_eq!(info.functions.get("inc"), Some(&vec![String::from("2"), String::from("+")]));
+        assert_eq!(info.variables.get("x"), Some(&5.0));
+        assert!(info.stack.is_empty());
+    }
+
+    #[test]
+    fn value_before_definition_is_evaluated() {
+        let mut info = session();
+        interpret_line(String::from("-sall { 1 + } inc fn y 4 inc def z 16 sqrt! def"), &mut info);
+        assert_eq!(info.variables.get("y"), Some(&5.0));
+        assert_eq!(info.variables.get("z"), Some(&4.0));
+    }
+}
+
+}
+
+pub mod builtins {
+    use std::collections::HashMap;
+    use super::interpet::*;
+    use super::namespaces::{all_namespaces, Namespace};
+    use super::math::{self, exact_integer, parse_number, Base, factorize, is_prime, snap, AngleMode, MAX_EXACT};
+    use super::modules::{import, Imported};
+    use super::natives::NativeFunction;
+    use super::output::Output;
+    use super::session::SessionInfo;
+    use super::storage::{escape_token, export_session, restore_session, save_session};
+
+    pub enum Flow {
+        Next,
+        Stop,
+    }
+
+    /// Per-line interpreter state that builtins can read and change.
+    pub struct Frame {
+        pub suppress_out: bool,
+        pub suppress_all: bool,
+        pub doc: Option<String>,
+        pub tokens: std::vec::IntoIter<String>,
+    }
+
+    impl Frame {
+        pub fn new(tokens: Vec<String>, suppress_all: bool) -> Self {
+            Frame {
+                suppress_out: false,
+                suppress_all,
+                doc: None,
+                tokens: tokens.into_iter(),
+            }
+        }
+
+        /// Whether optional output should be printed. Using it up clears a pending `-s`.
+        pub fn verbose(&mut self) -> bool {
+            let verbose = !self.suppress_out && !self.suppress_all;
+            self.suppress_out = false;
+            verbose
+        }
+    }
+
+    pub type Handler = fn(&mut SessionInfo, &mut Frame) -> Result<Flow, String>;
+
+    pub struct Builtin {
+        pub name: &'static str,
+        pub category: &'static str,
+        /// How many items the word needs on the stack; checked before the handler runs.
+        pub arity: usize,
+        pub effect: &'static str,
+        pub doc: &'static str,
+        pub handler: Handler,
+    }
+
+    const fn builtin(category: &'static str, name: &'static str, arity: usize,
+                     effect: &'static str, doc: &'static str, handler: Handler) -> Builtin {
+        Builtin { name, category, arity, effect, doc, handler }
+    }
+
+    pub const CATEGORIES: [&str; 7] = ["OPERATORS", "FUNCTIONS", "MATH", "STATISTICS", "UTILITIES", "PROGRAMMING", "FLAGS"];
+
+    /// Every built-in keyword, in the order `help` lists them.
+    pub static BUILTINS: &[Builtin] = &[
+        builtin("OPERATORS", "+", 2, "a b -- a+b", "(Addition) - adds the last two items on the stack.", add),
+        builtin("OPERATORS", "-", 2, "a b -- a-b", "(Subtraction) - subtracts the last two items on the stack.", sub),
+        builtin("OPERATORS", "*", 2, "a b -- a*b", "(Multiplication) - multiplies the last two items on the stack.", mul),
+        builtin("OPERATORS", "/", 2, "a b -- a/b", "(Division) - divides the last two items on the stack.", div),
+        builtin("OPERATORS", "**", 2, "a b -- a^b", "(Pow) - performs a to the power of b where a and b are the last two items on the stack.", pow),
+        builtin("OPERATORS", "%", 2, "a b -- a%b", "(Modulus) - Performs division and returns the remainder.", rem),
+        builtin("OPERATORS", "++", 1, "a -- a+1", "(Increment) - Increments the last item on the stack by 1.", inc),
+        builtin("OPERATORS", "--", 1, "a -- a-1", "(Decrement) - Decrements the last item on the stack by 1.", dec),
+        builtin("OPERATORS", "n!", 1, "n -- n!", "(Factorial) - Returns the factorial of the last item on the stack, which must be a whole number that is not negative.", factorial),
+        builtin("OPERATORS", "==", 2, "a b -- a==b", "(Equality) - Determines if the last two items on the stack are equal.", eq),
+        builtin("OPERATORS", "!=", 2, "a b -- a!=b", "(Not Equality) - Determines if the last two items on the stack are not equal.", ne),
+        builtin("OPERATORS", ">", 2, "a b -- a>b", "(Greater Than) - Determines if the last item is less than the second to last.", gt),
+        builtin("OPERATORS", "<", 2, "a b -- a<b", "(Less Than) - Determines if the last item is greater than the second to last", lt),
+        builtin("OPERATORS", "<=", 2, "a b -- a<=b", "(Less Than Equal To) - Determines if the last item is greater than or equal to the second to last.", le),
+        builtin("OPERATORS", ">=", 2, "a b -- a>=b", "(Greater Than Equal To) - Determines if the last item is less than or equal to the second to last.", ge),
+        builtin("OPERATORS", "<<", 2, "a n -- a<<n", "(Shift Left) - Shifts the bits of a number to the left a specified amount of times.", shl),
+        builtin("OPERATORS", ">>", 2, "a n -- a>>n", "(Shift Right) - Shifts the bits of a number to the right a specified amount of times.", shr),
+        builtin("OPERATORS", "&", 2, "a b -- a&b", "(AND) - Performs the logical AND operation on the last two items on the stack.", and),
+        builtin("OPERATORS", "|", 2, "a b -- a|b", "(OR) - Performs the logical OR operation on the last two items on the stack.", or),
+        builtin("OPERATORS", "^", 2, "a b -- a^b", "(XOR) - Performs the logical XOR operation on the last two items on the stack.", xor),
+        builtin("FUNCTIONS", "drop", 1, "a --", "Deletes the last item on the stack.", drop),
+        builtin("FUNCTIONS", "dup", 1, "a -- a a", "Duplicates the last item on the stack.", dup),
+        builtin("FUNCTIONS", "last", 0, "-- last", "Pushes the result of the previous REPL line.", last),
+        builtin("FUNCTIONS", "print", 1, "a --", "Prints and removes the last item on the stack.", print),
+        builtin("FUNCTIONS", "STACK_SIZE", 0, "-- n", "Pushes the size of the stack onto the stack.", stack_size),
+        builtin("FUNCTIONS", "ARGC", 0, "-- n", "Pushes the number of arguments passed to the script.", argc),
+        builtin("FUNCTIONS", "tohex", 1, "n -- \"0x..\"", "Pushes a whole number written in hexadecimal as a string, for puts.", to_hex),
+        builtin("FUNCTIONS", "tobin", 1, "n -- \"0b..\"", "Pushes a whole number written in binary as a string, for puts.", to_bin),
+        builtin("FUNCTIONS", "tooct", 1, "n -- \"0o..\"", "Pushes a whole number written in octal as a string, for puts.", to_oct),
+        builtin("FUNCTIONS", "tobits", 2, "n width -- \"bits\"", "Pushes the low bits of a whole number, in two's complement and padded to the width, as a string.", to_bits),
+        builtin("FUNCTIONS", "todec", 1, "n -- \"n\"", "Pushes a number written in decimal as a string, for puts.", to_dec),
+        builtin("FUNCTIONS", "arg", 1, "i -- arg", "Pushes the script argument at the given index, as a number or a string.", arg),
+        builtin("MATH", "sin", 1, "x -- sin(x)", "Sine of an angle in the current angle mode.", sin),
+        builtin("MATH", "cos", 1, "x -- cos(x)", "Cosine of an angle in the current angle mode.", cos),
+        builtin("MATH", "tan", 1, "x -- tan(x)", "Tangent of an angle in the current angle mode.", tan),
+        builtin("MATH", "asin", 1, "x -- asin(x)", "Arcsine, as an angle in the current angle mode.", asin),
+        builtin("MATH", "acos", 1, "x -- acos(x)", "Arccosine, as an angle in the current angle mode.", acos),
+        builtin("MATH", "atan", 1, "x -- atan(x)", "Arctangent, as an angle in the current angle mode.", atan),
+        builtin("MATH", "atan2", 2, "y x -- atan2(y,x)", "Angle of the point (x, y) from the x axis, in the current angle mode.", atan2),
+        builtin("MATH", "sinh", 1, "x -- sinh(x)", "Hyperbolic sine.", sinh),
+        builtin("MATH", "cosh", 1, "x -- cosh(x)", "Hyperbolic cosine.", cosh),
+        builtin("MATH", "tanh", 1, "x -- tanh(x)", "Hyperbolic tangent.", tanh),
+        builtin("MATH", "exp", 1, "x -- e^x", "e raised to the last item on the stack.", exp),
+        builtin("MATH", "ln", 1, "x -- ln(x)", "Natural logarithm.", ln),
+        builtin("MATH", "log10", 1, "x -- log10(x)", "Base 10 logarithm.", log10),
+        builtin("MATH", "log2", 1, "x -- log2(x)", "Base 2 logarithm.", log2),
+        builtin("MATH", "sqrt", 1, "x -- sqrt(x)", "Square root.", sqrt),
+        builtin("MATH", "cbrt", 1, "x -- cbrt(x)", "Cube root.", cbrt),
+        builtin("MATH", "floor", 1, "x -- floor(x)", "Rounds down to a whole number.", floor),
+        builtin("MATH", "ceil", 1, "x -- ceil(x)", "Rounds up to a whole number.", ceil),
+        builtin("MATH", "round", 1, "x -- round(x)", "Rounds to the nearest whole number, halves away from zero.", round),
+        builtin("MATH", "trunc", 1, "x -- trunc(x)", "Drops the fractional part.", trunc),
+        builtin("MATH", "abs", 1, "x -- |x|", "Absolute value.", abs),
+        builtin("MATH", "sign", 1, "x -- sign(x)", "Pushes -1, 0 or 1 for a negative, zero or positive number.", sign),
+        builtin("MATH", "mod", 2, "a b -- a mod b", "Integer modulo; unlike %, the result has the sign of b.", modulo),
+        builtin("MATH", "gcd", 2, "a b -- gcd(a,b)", "Greatest common divisor of two whole numbers.", gcd),
+        builtin("MATH", "lcm", 2, "a b -- lcm(a,b)", "Least common multiple of two whole numbers.", lcm),
+        builtin("MATH", "isprime", 1, "n -- 0|1", "Pushes 1 if the whole number is prime, otherwise 0.", isprime),
+        builtin("MATH", "powmod", 3, "b e m -- b^e mod m", "Modular exponentiation of whole numbers.", powmod),
+        builtin("MATH", "factor", 1, "n -- p1 p2 ...", "Replaces a whole number of at least 2 with its prime factors, smallest first.", factor),
+        builtin("MATH", "deg", 0, "--", "Sets the angle mode to degrees.", degrees),
+        builtin("MATH", "rad", 0, "--", "Sets the angle mode to radians (the default).", radians),
+        builtin("MATH", "grad", 0, "--", "Sets the angle mode to gradians.", gradians),
+        builtin("STATISTICS", "sort", 1, "x1..xn n -- sorted", "Sorts the top n items, smallest on the bottom. Use STACK_SIZE as n for the whole stack.", sort),
+        builtin("STATISTICS", "mean", 1, "x1..xn n -- mean", "Replaces the top n items with their mean.", mean),
+        builtin("STATISTICS", "median", 1, "x1..xn n -- median", "Replaces the top n items with their median.", median),
+        builtin("STATISTICS", "mode", 1, "x1..xn n -- mode", "Replaces the top n items with the most frequent one (the smallest, on a tie).", mode),
+        builtin("STATISTICS", "var", 1, "x1..xn n -- s^2", "Replaces the top n items with their sample variance.", sample_variance),
+        builtin("STATISTICS", "pvar", 1, "x1..xn n -- σ^2", "Replaces the top n items with their population variance.", population_variance),
+        builtin("STATISTICS", "stddev", 1, "x1..xn n -- s", "Replaces the top n items with their sample standard deviation.", sample_stddev),
+        builtin("STATISTICS", "pstddev", 1, "x1..xn n -- σ", "Replaces the top n items with their population standard deviation.", population_stddev),
+        builtin("STATISTICS", "percentile", 2, "x1..xn n p -- x", "Replaces the top n items with their pth percentile (0 to 100), interpolating between items.", percentile),
+        builtin("STATISTICS", "nmin", 1, "x1..xn n -- min", "Replaces the top n items with the smallest one.", nmin),
+        builtin("STATISTICS", "nmax", 1, "x1..xn n -- max", "Replaces the top n items with the largest one.", nmax),
+        builtin("STATISTICS", "linreg", 1, "x1 y1..xn yn n -- m b", "Fits the line y = mx + b to n points by least squares and pushes its slope and intercept.", linreg),
+        builtin("UTILITIES", "functions", 0, "--", "Prints a list of all user-defined functions.", functions),
+        builtin("UTILITIES", "variables", 0, "--", "Prints a list of all user-defined variables, including those of namespaces.", variables),
+        builtin("UTILITIES", "constants", 0, "--", "Prints a list of all constants, including those of namespaces and the built-in ones.", constants),
+        builtin("UTILITIES", "namespaces", 0, "--", "Prints a list of all namespaces, nested ones as parent::child.", namespaces),
+        builtin("UTILITIES", "identifiers", 0, "--", "Prints a list of all user-defined identifiers.", identifiers),
+        builtin("UTILITIES", "script", 0, "--", "Prints a list of tokens in {code}.", script),
+        builtin("UTILITIES", "pop_ident", 0, "ident --", "Pops the last identifier off the identifier stack.", pop_ident),
+        builtin("UTILITIES", "push_ident", 1, "a -- ident", "Pushes the last number onto the identifier stack.", push_ident),
+        builtin("UTILITIES", "out", 0, "{msg} --", "Prints a desired message from [Out].", out_msg),
+        builtin("UTILITIES", "oout", 0, "{msg} --", "Prints a suppressable desired message from [Out].", oout),
+        builtin("UTILITIES", "warn", 0, "{msg} --", "Prints a desired message from [Warn].", warn_msg),
+        builtin("UTILITIES", "owarn", 0, "{msg} --", "Prints a suppressable desired message from [Warn].", owarn),
+        builtin("UTILITIES", "err", 0, "{msg} --", "Prints a desired message from [Error].", err),
+        builtin("UTILITIES", "oerr", 0, "{msg} --", "Prints a suppressable desired message from [Error].", oerr),
+        builtin("UTILITIES", "flush", 0, "{msg} --", "Prints a desired message w/o any source.", flush),
+        builtin("UTILITIES", "puts", 0, "\"string\" --", "Prints the last string literal, with escapes, and no newline.", puts),
+        builtin("UTILITIES", "pushch", 1, "{code} a -- {code}", "Appends the last number to the last token in {code}.", pushch),
+        builtin("UTILITIES", "formats", 1, "ident a --", "(Not implemented) - Formats a number.", formats),
+        builtin("UTILITIES", "def", 1, "name a --", "Defines a variable using the last identifier and number.", def),
+        builtin("UTILITIES", "const", 1, "name a --", "Defines a constant using the last identifier and number. Constants cannot be redefined or reset.", constant),
+        builtin("UTILITIES", "fn", 0, "{code} name --", "Defines a function using the last {code} and identifier.", function),
+        builtin("UTILITIES", "namespace", 0, "{code} name --", "Runs {code} and collects the functions, variables, constants and namespaces it defines into a namespace.", namespace),
+        builtin("UTILITIES", "use", 0, "--", "Opens the namespace named after it, so its functions can be called unqualified.", use_namespace),
+        builtin("UTILITIES", "alias", 0, "namespace name --", "Makes the last identifier another name for a namespace.", alias),
+        builtin("UTILITIES", "{", 0, "--", "Begins adding tokens to code.", nothing),
+        builtin("UTILITIES", "}", 0, "--", "Ends adding tokens to innermost scope.", nothing),
+        builtin("UTILITIES", "import", 0, "--", "Imports the module named after it (or by the last identifier, at the end of a line) into a namespace, once.", import_module),
+        builtin("UTILITIES", "load_file", 0, "path --", "Loads a file into the calculator.", load),
+        builtin("UTILITIES", "save_session", 0, "path --", "Saves functions, variables, constants, namespaces and the stack to a file.", save),
+        builtin("UTILITIES", "restore_session", 0, "path --", "Replaces the session with one saved by save_session.", restore),
+        builtin("UTILITIES", "export", 0, "path --", "Writes every definition in the session to a file as CSL source.", export),
+        builtin("UTILITIES", "reset", 0, "{names} --", "If arguments are provided in {code}, it resets those specific variables/functions, otherwise, it resets all functions and variables. Constants are kept.", reset),
+        builtin("UTILITIES", "concatf", 0, "ident -- {code}", "Concatenates the top identifier to {code}.", concatf),
+        builtin("UTILITIES", "clear", 0, "--", "Clears the screen.", clear),
+        builtin("UTILITIES", "ansi", 0, "{cmd} --", "Executes ANSI commands within {code}.", ansi),
+        builtin("UTILITIES", "help", 0, "--", "Prints this page, or with a word after it, the entry for that keyword or function.", help),
+        builtin("UTILITIES", "complete", 0, "--", "Lists the keywords, functions and variables starting with the word after it.", complete),
+        builtin("UTILITIES", "credits", 0, "--", "Prints credits.", credits),
+        builtin("UTILITIES", "about", 0, "--", "Prints more about this project.", about),
+        builtin("UTILITIES", "clear_code", 0, "{code} --", "Clears {code}, this is automatically done after execution ends.", clear_code),
+        builtin("UTILITIES", "rpn", 0, "--", "Toggles RPN mode, where the stack persists between lines.", rpn),
+        builtin("UTILITIES", "undo", 0, "--", "Undoes the last REPL entry (stack, variables and functions).", undo),
+        builtin("UTILITIES", "redo", 0, "--", "Redoes the last undone REPL entry.", redo),
+        builtin("UTILITIES", "panel", 0, "--", "Toggles a panel showing the stack, identifiers and {code} after each line.", panel),
+        builtin("UTILITIES", "theme", 0, "{value} key --", "Changes a theme setting, such as the prompt or the color of [Out].", theme),
+        builtin("UTILITIES", "hex", 0, "--", "Shows whole-number results in hexadecimal.", show_hex),
+        builtin("UTILITIES", "bin", 0, "--", "Shows whole-number results in binary.", show_bin),
+        builtin("UTILITIES", "oct", 0, "--", "Shows whole-number results in octal.", show_oct),
+        builtin("UTILITIES", "dec", 0, "--", "Shows results in decimal (the default).", show_dec),
+        builtin("UTILITIES", "color", 0, "--", "Toggles colored output.", color),
+        builtin("UTILITIES", "quit", 0, "--", "Prompts the user to quit.", nothing),
+        builtin("PROGRAMMING", "if", 1, "{code} cond --", "If statement; executes {code} if last item on the stack is 1.", if_block),
+        builtin("PROGRAMMING", "elif", 1, "{code} cond --", "Else if statement; executes {code} if last item on the stack is 1 and if statement is false.", elif_block),
+        builtin("PROGRAMMING", "else", 0, "{code} --", "Else statement; executes {code} if last if/elif is false.", else_block),
+        builtin("PROGRAMMING", "times", 1, "{code} n --", "Executes {code} specified amount of times.", times),
+        builtin("PROGRAMMING", "break", 0, "--", "Breaks execution.", stop),
+        builtin("PROGRAMMING", "exit", 0, "code --", "Exits with the last item on the stack as the status code (0 if empty).", exit_with),
+        builtin("FLAGS", "-s", 0, "--", "(Suppress output) - Suppresses any optional function output.", suppress),
+        builtin("FLAGS", "-sall", 0, "--", "(Suppress all outputs) - Suppresses all optional function outputs.", suppress_all),
+    ];
+
+    pub fn lookup(name: &str) -> Option<&'static Builtin> {
+        BUILTINS.iter().find(|builtin| builtin.name == name)
+    }
+
+    /// A constant every session has. It cannot be redefined, shadowed or reset.
+    pub struct Constant {
+        pub name: &'static str,
+        pub value: f32,
+        pub doc: &'static str,
+    }
+
+    pub static CONSTANTS: &[Constant] = &[
+        Constant { name: "pi", value: std::f32::consts::PI, doc: "The ratio of a circle's circumference to its diameter." },
+        Constant { name: "e", value: std::f32::consts::E, doc: "Euler's number, the base of the natural logarithm." },
+        Constant { name: "tau", value: std::f32::consts::TAU, doc: "2 * pi, the number of radians in a full turn." },
+        Constant { name: "phi", value: 1.618_034, doc: "The golden ratio, (1 + sqrt(5)) / 2." },
+        Constant { name: "inf", value: f32::INFINITY, doc: "Positive infinity." },
+        Constant { name: "nan", value: f32::NAN, doc: "Not a number." },
+    ];
+
+    pub fn builtin_constant(name: &str) -> Option<&'static Constant> {
+        CONSTANTS.iter().find(|constant| constant.name == name)
+    }
+
+    /// Explains why `name` cannot be bound, if it is a built-in word or constant, which are found first.
+    pub fn check_builtin(name: &str) -> Result<(), String> {
+        if lookup(name).is_some() {
+            return Err(format!("'{}' is a built-in word and cannot be redefined.", name));
+        }
+        if builtin_constant(name).is_some() {
+            return Err(format!("'{}' is a built-in constant and cannot be redefined.", name));
+        }
+        Ok(())
+    }
+
+    /// Every keyword, function, variable and constant name starting with `prefix`.
+    pub fn completions(prefix: &str, info: &SessionInfo) -> Vec<String> {
+        let mut names: Vec<String> = BUILTINS.iter().map(|builtin| builtin.name.to_owned()).collect();
+        names.extend(info.functions.keys().cloned());
+        names.extend(info.variables.keys().cloned());
+        names.extend(info.constants.keys().cloned());
+        names.extend(CONSTANTS.iter().map(|constant| constant.name.to_owned()));
+        names.extend(info.natives.keys().cloned());
+        for (path, namespace) in info.all_namespaces() {
+            let words = namespace.functions.keys()
+                .chain(namespace.natives.keys())
+                .chain(namespace.variables.keys())
+                .chain(namespace.constants.keys());
+            for name in words {
+                names.push(name.to_owned());
+                names.push(format!("{}::{}", path, name));
+            }
+        }
+
+        names.retain(|name| name.starts_with(prefix));
+        names.sort();
+        names.dedup();
+        names
+    }
+
+    fn pop2(info: &mut SessionInfo) -> (f32, f32) {
+        let rhs = info.stack.pop().unwrap();
+        let lhs = info.stack.pop().unwrap();
+        (lhs, rhs)
+    }
+
+    fn binary(info: &mut SessionInfo, op: fn(f32, f32) -> f32) -> Result<Flow, String> {
+        let (lhs, rhs) = pop2(info);
+        info.stack.push(op(lhs, rhs));
+        Ok(Flow::Next)
+    }
+
+    fn bitwise(info: &mut SessionInfo, op: fn(i32, i32) -> i32) -> Result<Flow, String> {
+        let (lhs, rhs) = pop2(info);
+        info.stack.push(op(lhs as i32, rhs as i32) as f32);
+        Ok(Flow::Next)
+    }
+
+    fn compare(info: &mut SessionInfo, op: fn(f32, f32) -> bool) -> Result<Flow, String> {
+        let (lhs, rhs) = pop2(info);
+        info.stack.push(if op(lhs, rhs) { 1.0 } else { 0.0 });
+        Ok(Flow::Next)
+    }
+
+    fn unary(info: &mut SessionInfo, op: fn(f64) -> f64) -> Result<Flow, String> {
+        let value = info.stack.pop().unwrap() as f64;
+        info.stack.push(op(value) as f32);
+        Ok(Flow::Next)
+    }
+
+    /// Applies a trigonometric function to an angle in the current angle mode.
+    fn trig(info: &mut SessionInfo, op: fn(f64) -> f64) -> Result<Flow, String> {
+        let angle = info.angle.to_radians(info.stack.pop().unwrap() as f64);
+        info.stack.push(snap(op(angle)));
+        Ok(Flow::Next)
+    }
+
+    /// Applies an inverse trigonometric function, giving an angle in the current angle mode.
+    fn inverse_trig(info: &mut SessionInfo, op: fn(f64) -> f64) -> Result<Flow, String> {
+        let value = info.stack.pop().unwrap() as f64;
+        info.stack.push(info.angle.from_radians(op(value)) as f32);
+        Ok(Flow::Next)
+    }
+
+    /// Pops the top `count` items, oldest first, if they are all exact whole numbers.
+    /// Nothing is popped otherwise.
+    fn pop_integers(info: &mut SessionInfo, count: usize, word: &str) -> Result<Vec<i64>, String> {
+        let args = &info.stack[info.stack.len() - count..];
+        let mut integers = Vec::new();
+        for value in args {
+            if value.fract() != 0.0 || !value.is_finite() {
+                return Err(format!("'{}' requires whole numbers, got {}.", word, value));
+            }
+            integers.push(exact_integer(*value).ok_or(format!(
+                "'{}' only works on whole numbers up to {}, got {}.", word, MAX_EXACT, value))?);
+        }
+        info.stack.truncate(info.stack.len() - count);
+        Ok(integers)
+    }
+
+    /// Pops the count `n` and the `n * width` items under it, oldest first, once both are
+    /// checked. `above` is how many arguments sit on top of the count; they are left alone.
+    fn pop_items(info: &mut SessionInfo, word: &str, above: usize, width: usize, least: usize) -> Result<Vec<f32>, String> {
+        let count = info.stack[info.stack.len() - 1 - above];
+        if count.fract() != 0.0 || count < least as f32 {
+            return Err(format!("'{}' requires a whole count of at least {}, got {}.", word, least, count));
+        }
+        let available = info.stack.len() - 1 - above;
+        let needed = (count as usize).checked_mul(width)
+            .filter(|needed| *needed <= available)
+            .ok_or(format!("'{}' needs {} items, but there are only {}.", word, count * width as f32, available))?;
+        let args = info.stack.split_off(info.stack.len() - above);
+        info.stack.pop();
+        let items = info.stack.split_off(info.stack.len() - needed);
+        info.stack.extend(args);
+        Ok(items)
+    }
+
+    fn pop_sorted(info: &mut SessionInfo, word: &str, above: usize) -> Result<Vec<f32>, String> {
+        let mut items = pop_items(info, word, above, 1, 1)?;
+        items.sort_by(f32::total_cmp);
+        Ok(items)
+    }
+
+    /// Pushes the top of the stack written in `base` as a string literal.
+    fn convert(info: &mut SessionInfo, base: Base) -> Result<Flow, String> {
+        let value = *info.stack.last().unwrap();
+        if base != Base::Decimal && value.fract() != 0.0 {
+            return Err(format!("Only whole numbers can be written in {}, got {}.", base.name(), value));
+        }
+        info.stack.pop();
+        info.idents.push(format!("\"{}\"", base.format(value)));
+        Ok(Flow::Next)
+    }
+
+    fn set_base(info: &mut SessionInfo, frame: &mut Frame, base: Base) -> Result<Flow, String> {
+        info.base = base;
+        if frame.verbose() {
+            info.output.out(&format!("Showing results in {}.", base.name()));
+        }
+        Ok(Flow::Next)
+    }
+
+    fn set_angle(info: &mut SessionInfo, frame: &mut Frame, mode: AngleMode) -> Result<Flow, String> {
+        info.angle = mode;
+        if frame.verbose() {
+            info.output.out(&format!("Angle mode: {}.", mode.name()));
+        }
+        Ok(Flow::Next)
+    }
+
+    fn pop_ident_arg(info: &mut SessionInfo) -> Result<String, String> {
+        info.idents.pop().ok_or(String::from("Insufficient arguments!"))
+    }
+
+    fn add(info: &mut SessionInfo, _: &mut Frame) -> Result<Flow, String> { binary(info, |a, b| a + b) }
+    fn sub(info: &mut SessionInfo, _: &mut Frame) -> Result<Flow, String> { binary(info, |a, b| a - b) }
+    fn mul(info: &mut SessionInfo, _: &mut Frame) -> Result<Flow, String> { binary(info, |a, b| a * b) }
+    fn div(info: &mut SessionInfo, _: &mut Frame) -> Result<Flow, String> { binary(info, |a, b| a / b) }
+    fn rem(info: &mut SessionInfo, _: &mut Frame) -> Result<Flow, String> { binary(info, |a, b| a % b) }
+    fn pow(info: &mut SessionInfo, _: &mut Frame) -> Result<Flow, String> { binary(info, f32::powf) }
+    fn shl(info: &mut SessionInfo, _: &mut Frame) -> Result<Flow, String> { bitwise(info, |a, b| a << b) }
+    fn shr(info: &mut SessionInfo, _: &mut Frame) -> Result<Flow, String> { bitwise(info, |a, b| a >> b) }
+    fn and(info: &mut SessionInfo, _: &mut Frame) -> Result<Flow, String> { bitwise(info, |a, b| a & b) }
+    fn or(info: &mut SessionInfo, _: &mut Frame) -> Result<Flow, String> { bitwise(info, |a, b| a | b) }
+    fn xor(info: &mut SessionInfo, _: &mut Frame) -> Result<Flow, String> { bitwise(info, |a, b| a ^ b) }
+    fn eq(info: &mut SessionInfo, _: &mut Frame) -> Result<Flow, String> { compare(info, |a, b| a == b) }
+    fn ne(info: &mut SessionInfo, _: &mut Frame) -> Result<Flow, String> { compare(info, |a, b| a != b) }
+    fn gt(info: &mut SessionInfo, _: &mut Frame) -> Result<Flow, String> { compare(info, |a, b| a > b) }
+    fn lt(info: &mut SessionInfo, _: &mut Frame) -> Result<Flow, String> { compare(info, |a, b| a < b) }
+    fn ge(info: &mut SessionInfo, _: &mut Frame) -> Result<Flow, String> { compare(info, |a, b| a >= b) }
+    fn le(info: &mut SessionInfo, _: &mut Frame) -> Result<Flow, String> { compare(info, |a, b| a <= b) }
+
+    fn inc(info: &mut SessionInfo, _: &mut Frame) -> Result<Flow, String> {
+        let num = info.stack.pop().unwrap();
+        info.stack.push(num + 1.0);
+        Ok(Flow::Next)
+    }
+
+    fn dec(info: &mut SessionInfo, _: &mut Frame) -> Result<Flow, String> {
+        let num = info.stack.pop().unwrap();
+        info.stack.push(num - 1.0);
+        Ok(Flow::Next)
+    }
+
+    fn factorial(info: &mut SessionInfo, _: &mut Frame) -> Result<Flow, String> {
+        let num = *info.stack.last().unwrap();
+        if num.fract() != 0.0 || num < 0.0 {
+            return Err(format!("'n!' requires a whole number that is not negative, got {}.", num));
+        }
+        info.stack.pop();
+
+        let mut res: f64 = 1.0;
+        let mut i = 2.0;
+        while i <= num as f64 && res.is_finite() {
+            res *= i;
+            i += 1.0;
+        }
+        info.stack.push(res as f32);
+        Ok(Flow::Next)
+    }
+
+    fn sin(info: &mut SessionInfo, _: &mut Frame) -> Result<Flow, String> { trig(info, f64::sin) }
+    fn cos(info: &mut SessionInfo, _: &mut Frame) -> Result<Flow, String> { trig(info, f64::cos) }
+    fn tan(info: &mut SessionInfo, _: &mut Frame) -> Result<Flow, String> { trig(info, f64::tan) }
+    fn asin(info: &mut SessionInfo, _: &mut Frame) -> Result<Flow, String> { inverse_trig(info, f64::asin) }
+    fn acos(info: &mut SessionInfo, _: &mut Frame) -> Result<Flow, String> { inverse_trig(info, f64::acos) }
+    fn atan(info: &mut SessionInfo, _: &mut Frame) -> Result<Flow, String> { inverse_trig(info, f64::atan) }
+    fn sinh(info: &mut SessionInfo, _: &mut Frame) -> Result<Flow, String> { unary(info, f64::sinh) }
+    fn cosh(info: &mut SessionInfo, _: &mut Frame) -> Result<Flow, String> { unary(info, f64::cosh) }
+    fn tanh(info: &mut SessionInfo, _: &mut Frame) -> Result<Flow, String> { unary(info, f64::tanh) }
+    fn exp(info: &mut SessionInfo, _: &mut Frame) -> Result<Flow, String> { unary(info, f64::exp) }
+    fn ln(info: &mut SessionInfo, _: &mut Frame) -> Result<Flow, String> { unary(info, f64::ln) }
+    fn log10(info: &mut SessionInfo, _: &mut Frame) -> Result<Flow, String> { unary(info, f64::log10) }
+    fn log2(info: &mut SessionInfo, _: &mut Frame) -> Result<Flow, String> { unary(info, f64::log2) }
+    fn sqrt(info: &mut SessionInfo, _: &mut Frame) -> Result<Flow, String> { unary(info, f64::sqrt) }
+    fn cbrt(info: &mut SessionInfo, _: &mut Frame) -> Result<Flow, String> { unary(info, f64::cbrt) }
+
+    fn floor(info: &mut SessionInfo, _: &mut Frame) -> Result<Flow, String> { unary(info, f64::floor) }
+    fn ceil(info: &mut SessionInfo, _: &mut Frame) -> Result<Flow, String> { unary(info, f64::ceil) }
+    fn round(info: &mut SessionInfo, _: &mut Frame) -> Result<Flow, String> { unary(info, f64::round) }
+    fn trunc(info: &mut SessionInfo, _: &mut Frame) -> Result<Flow, String> { unary(info, f64::trunc) }
+    fn abs(info: &mut SessionInfo, _: &mut Frame) -> Result<Flow, String> { unary(info, f64::abs) }
+
+    fn sign(info: &mut SessionInfo, _: &mut Frame) -> Result<Flow, String> {
+        unary(info, |x| if x == 0.0 { 0.0 } else { x.signum() })
+    }
+
+    fn modulo(info: &mut SessionInfo, _: &mut Frame) -> Result<Flow, String> {
+        if *info.stack.last().unwrap() == 0.0 {
+            return Err(String::from("'mod' cannot divide by zero."));
+        }
+        let args = pop_integers(info, 2, "mod")?;
+        let (a, b) = (args[0], args[1]);
+        info.stack.push(((a % b + b) % b) as f32);
+        Ok(Flow::Next)
+    }
+
+    fn gcd(info: &mut SessionInfo, _: &mut Frame) -> Result<Flow, String> {
+        let args = pop_integers(info, 2, "gcd")?;
+        info.stack.push(math::gcd(args[0], args[1]) as f32);
+        Ok(Flow::Next)
+    }
+
+    fn lcm(info: &mut SessionInfo, _: &mut Frame) -> Result<Flow, String> {
+        let args = pop_integers(info, 2, "lcm")?;
+        info.stack.push(math::lcm(args[0], args[1]) as f32);
+        Ok(Flow::Next)
+    }
+
+    fn isprime(info: &mut SessionInfo, _: &mut Frame) -> Result<Flow, String> {
+        let args = pop_integers(info, 1, "isprime")?;
+        info.stack.push(if is_prime(args[0]) { 1.0 } else { 0.0 });
+        Ok(Flow::Next)
+    }
+
+    fn powmod(info: &mut SessionInfo, _: &mut Frame) -> Result<Flow, String> {
+        let (exponent, modulus) = (info.stack[info.stack.len() - 2], info.stack[info.stack.len() - 1]);
+        if exponent < 0.0 {
+            return Err(format!("'powmod' requires an exponent that is not negative, got {}.", exponent));
+        }
+        if modulus <= 0.0 {
+            return Err(format!("'powmod' requires a positive modulus, got {}.", modulus));
+        }
+        let args = pop_integers(info, 3, "powmod")?;
+        info.stack.push(math::powmod(args[0], args[1], args[2]) as f32);
+        Ok(Flow::Next)
+    }
+
+    fn factor(info: &mut SessionInfo, _: &mut Frame) -> Result<Flow, String> {
+        let n = *info.stack.last().unwrap();
+        if n < 2.0 {
+            return Err(format!("'factor' requires a whole number of at least 2, got {}.", n));
+        }
+        let args = pop_integers(info, 1, "factor")?;
+        info.stack.extend(factorize(args[0]).into_iter().map(|factor| factor as f32));
+        Ok(Flow::Next)
+    }
+
+    fn sort(info: &mut SessionInfo, _: &mut Frame) -> Result<Flow, String> {
+        let items = pop_sorted(info, "sort", 0)?;
+        info.stack.extend(items);
+        Ok(Flow::Next)
+    }
+
+    fn mean(info: &mut SessionInfo, _: &mut Frame) -> Result<Flow, String> {
+        let items = pop_items(info, "mean", 0, 1, 1)?;
+        info.stack.push(math::mean(&items) as f32);
+        Ok(Flow::Next)
+    }
+
+    fn median(info: &mut SessionInfo, _: &mut Frame) -> Result<Flow, String> {
+        let items = pop_sorted(info, "median", 0)?;
+        info.stack.push(math::median(&items) as f32);
+        Ok(Flow::Next)
+    }
+
+    fn mode(info: &mut SessionInfo, _: &mut Frame) -> Result<Flow, String> {
+        let items = pop_sorted(info, "mode", 0)?;
+        info.stack.push(math::mode(&items));
+        Ok(Flow::Next)
+    }
+
+    fn spread(info: &mut SessionInfo, word: &str, sample: bool, root: bool) -> Result<Flow, String> {
+        let items = pop_items(info, word, 0, 1, if sample { 2 } else { 1 })?;
+        let variance = math::variance(&items, sample);
+        info.stack.push(if root { variance.sqrt() } else { variance } as f32);
+        Ok(Flow::Next)
+    }
+
+    fn sample_variance(info: &mut SessionInfo, _: &mut Frame) -> Result<Flow, String> { spread(info, "var", true, false) }
+    fn population_variance(info: &mut SessionInfo, _: &mut Frame) -> Result<Flow, String> { spread(info, "pvar", false, false) }
+    fn sample_stddev(info: &mut SessionInfo, _: &mut Frame) -> Result<Flow, String> { spread(info, "stddev", true, true) }
+    fn population_stddev(info: &mut SessionInfo, _: &mut Frame) -> Result<Flow, String> { spread(info, "pstddev", false, true) }
+
+    fn percentile(info: &mut SessionInfo, _: &mut Frame) -> Result<Flow, String> {
+        let p = *info.stack.last().unwrap();
+        if !(0.0..=100.0).contains(&p) {
+            return Err(format!("'percentile' requires a percentile from 0 to 100, got {}.", p));
+        }
+        let items = pop_sorted(info, "percentile", 1)?;
+        info.stack.pop();
+        info.stack.push(math::percentile(&items, p as f64) as f32);
+        Ok(Flow::Next)
+    }
+
+    fn nmin(info: &mut SessionInfo, _: &mut Frame) -> Result<Flow, String> {
+        let items = pop_sorted(info, "nmin", 0)?;
+        info.stack.push(items[0]);
+        Ok(Flow::Next)
+    }
+
+    fn nmax(info: &mut SessionInfo, _: &mut Frame) -> Result<Flow, String> {
+        let items = pop_sorted(info, "nmax", 0)?;
+        info.stack.push(items[items.len() - 1]);
+        Ok(Flow::Next)
+    }
+
+    fn linreg(info: &mut SessionInfo, _: &mut Frame) -> Result<Flow, String> {
+        let items = pop_items(info, "linreg", 0, 2, 2)?;
+        let points: Vec<(f32, f32)> = items.chunks(2).map(|point| (point[0], point[1])).collect();
+        let Some((slope, intercept)) = math::linear_regression(&points) else {
+            // Put the points and their count back.
+            info.stack.extend(items);
+            info.stack.push(points.len() as f32);
+            return Err(String::from("'linreg' needs points with at least two different x values."));
+        };
+        info.stack.push(slope as f32);
+        info.stack.push(intercept as f32);
+        Ok(Flow::Next)
+    }
+
+    fn atan2(info: &mut SessionInfo, _: &mut Frame) -> Result<Flow, String> {
+        let (y, x) = pop2(info);
+        info.stack.push(info.angle.from_radians((y as f64).atan2(x as f64)) as f32);
+        Ok(Flow::Next)
+    }
+
+    fn degrees(info: &mut SessionInfo, frame: &mut Frame) -> Result<Flow, String> { set_angle(info, frame, AngleMode::Degrees) }
+    fn radians(info: &mut SessionInfo, frame: &mut Frame) -> Result<Flow, String> { set_angle(info, frame, AngleMode::Radians) }
+    fn gradians(info: &mut SessionInfo, frame: &mut Frame) -> Result<Flow, String> { set_angle(info, frame, AngleMode::Gradians) }
+
+    fn drop(info: &mut SessionInfo, _: &mut Frame) -> Result<Flow, String> {
+        info.stack.pop();
+        Ok(Flow::Next)
+    }
+
+    fn dup(info: &mut SessionInfo, _: &mut Frame) -> Result<Flow, String> {
+        info.stack.push(*info.stack.last().unwrap());
+        Ok(Flow::Next)
+    }
+
+    fn last(info: &mut SessionInfo, _: &mut Frame) -> Result<Flow, String> {
+        info.stack.push(info.last);
+        Ok(Flow::Next)
+    }
+
+    fn print(info: &mut SessionInfo, _: &mut Frame) -> Result<Flow, String> {
+        let item = info.stack.pop().unwrap();
+        info.output.println(&format!("{item}"));
+        Ok(Flow::Next)
+    }
+
+    fn stack_size(info: &mut SessionInfo, _: &mut Frame) -> Result<Flow, String> {
+        let size = info.stack.len();
+        info.stack.push(size as f32);
+        Ok(Flow::Next)
+    }
+
+    fn argc(info: &mut SessionInfo, _: &mut Frame) -> Result<Flow, String> {
+        let count = info.args.len();
+        info.stack.push(count as f32);
+        Ok(Flow::Next)
+    }
+
+    fn to_hex(info: &mut SessionInfo, _: &mut Frame) -> Result<Flow, String> { convert(info, Base::Hexadecimal) }
+    fn to_bin(info: &mut SessionInfo, _: &mut Frame) -> Result<Flow, String> { convert(info, Base::Binary) }
+    fn to_oct(info: &mut SessionInfo, _: &mut Frame) -> Result<Flow, String> { convert(info, Base::Octal) }
+    fn to_dec(info: &mut SessionInfo, _: &mut Frame) -> Result<Flow, String> { convert(info, Base::Decimal) }
+
+    fn to_bits(info: &mut SessionInfo, _: &mut Frame) -> Result<Flow, String> {
+        let args = pop_integers(info, 2, "tobits")?;
+        let (value, width) = (args[0], args[1]);
+        if !(1..=64).contains(&width) {
+            return Err(format!("'tobits' takes a width from 1 to 64, got {}.", width));
+        }
+        info.idents.push(format!("\"{}\"", math::bits(value, width as u32)));
+        Ok(Flow::Next)
+    }
+
+    fn show_hex(info: &mut SessionInfo, frame: &mut Frame) -> Result<Flow, String> { set_base(info, frame, Base::Hexadecimal) }
+    fn show_bin(info: &mut SessionInfo, frame: &mut Frame) -> Result<Flow, String> { set_base(info, frame, Base::Binary) }
+    fn show_oct(info: &mut SessionInfo, frame: &mut Frame) -> Result<Flow, String> { set_base(info, frame, Base::Octal) }
+    fn show_dec(info: &mut SessionInfo, frame: &mut Frame) -> Result<Flow, String> { set_base(info, frame, Base::Decimal) }
+
+    fn arg(info: &mut SessionInfo, _: &mut Frame) -> Result<Flow, String> {
+        let index = info.stack.pop().unwrap();
+        if index < 0.0 || index as usize >= info.args.len() {
+            return Err(format!("No argument at index {}.", index));
+        }
+
+        let arg = &info.args[index as usize];
+        if let Some(num) = parse_number(arg) {
+            info.stack.push(num);
+        } else {
+            info.idents.push(escape_token(&format!("\"{}\"", arg)));
+        }
+        Ok(Flow::Next)
+    }
+
+    fn functions(info: &mut SessionInfo, _: &mut Frame) -> Result<Flow, String> {
+        if info.functions.is_empty() && info.natives.is_empty() && info.namespaces.is_empty() {
+            info.output.out("None");
+            return Ok(Flow::Next);
+        }
+
+        info.output.out("\n");
+
+        let output = &mut info.output;
+        for (path, namespace) in all_namespaces(&info.namespaces) {
+            output.println(&format!("\tNAMESPACE: {}{}{}{}{}",
+                output.paint(UNDERLINE), output.paint(BOLD), path, output.paint(DEFAULT), output.paint(GREEN)));
+            for func in namespace.functions.keys() {
+                output.println(&format!(
+                    "\t{}{}{}{} = {:?}",
+                    output.paint(BOLD),
+                    func,
+                    output.paint(DEFAULT),
+                    output.paint(GREEN),
+                    namespace.functions.get(func).unwrap()
+                ));
+                print_doc(output, namespace.docs.get(func));
+            }
+            print_natives(output, &namespace.natives);
+        }
+
+        info.output.println(&format!("\t{}{}N/A{}{}", info.output.paint(UNDERLINE), info.output.paint(BOLD), info.output.paint(DEFAULT), info.output.paint(GREEN)));
+        for function in info.functions.keys() {
+            info.output.println(&format!(
+                "\t{}{}{}{} = {:?}",
+                info.output.paint(BOLD),
+                function,
+                info.output.paint(DEFAULT),
+                info.output.paint(GREEN),
+                info.functions.get(function).unwrap()
+            ));
+            print_doc(&mut info.output, info.docs.get(function));
+        }
+        print_natives(&mut info.output, &info.natives);
+        Ok(Flow::Next)
+    }
+
+    fn print_natives(output: &mut Output, natives: &HashMap<String, NativeFunction>) {
+        for (name, native) in natives {
+            output.println(&format!("\t{}{}{}{} = native ({} argument(s))",
+                output.paint(BOLD), name, output.paint(DEFAULT), output.paint(GREEN), native.arity));
+        }
+    }
+
+    fn variables(info: &mut SessionInfo, _: &mut Frame) -> Result<Flow, String> {
+        let values = qualified_values(&info.variables, &info.namespaces, |namespace| &namespace.variables);
+        print_values(&mut info.output, values);
+        Ok(Flow::Next)
+    }
+
+    fn constants(info: &mut SessionInfo, _: &mut Frame) -> Result<Flow, String> {
+        let mut values = qualified_values(&info.constants, &info.namespaces, |namespace| &namespace.constants);
+        values.extend(CONSTANTS.iter().map(|constant| (constant.name.to_owned(), constant.value)));
+        print_values(&mut info.output, values);
+        Ok(Flow::Next)
+    }
+
+    /// The session's own values, then those of every namespace as `path::name`.
+    fn qualified_values(own: &HashMap<String, f32>, namespaces: &[Namespace],
+                        select: fn(&Namespace) -> &HashMap<String, f32>) -> Vec<(String, f32)> {
+        let mut values: Vec<(String, f32)> = own.iter().map(|(name, value)| (name.to_owned(), *value)).collect();
+        for (path, namespace) in all_namespaces(namespaces) {
+            for (name, value) in select(namespace) {
+                values.push((format!("{}::{}", path, name), *value));
+            }
+        }
+        values
+    }
+
+    fn print_values(output: &mut Output, values: Vec<(String, f32)>) {
+        if values.is_empty() {
+            output.out("None");
+            return;
+        }
+
+        output.out("\n");
+        for (name, value) in values {
+            output.println(&format!("\t{}{}{}{} = {}",
+            output.paint(BOLD),
+            name,
+            output.paint(DEFAULT),
+            output.paint(GREEN),
+            value));
+        }
+    }
+
+    fn namespaces(info: &mut SessionInfo, _: &mut Frame) -> Result<Flow, String> {
+        if info.namespaces.is_empty() {
+            info.output.out("None");
+            return Ok(Flow::Next);
+        }
+
+        info.output.out("\n");
+
+        let output = &mut info.output;
+        for (path, _) in all_namespaces(&info.namespaces) {
+            output.println(&format!("\t{}{}{}{}", output.paint(BOLD), path, output.paint(DEFAULT), output.paint(GREEN)));
+        }
+        Ok(Flow::Next)
+    }
+
+    fn identifiers(info: &mut SessionInfo, _: &mut Frame) -> Result<Flow, String> {
+        if info.idents.is_empty() {
+            info.output.out("None");
+            return Ok(Flow::Stop);
+        }
+
+        info.output.out("\n");
+        for ident in &info.idents {
+            info.output.println(&format!("\t{}{}{}",
+            info.output.paint(UNDERLINE), ident, info.output.paint(NO_UNDERLINE)));
+        }
+        info.output.println("");
+        Ok(Flow::Next)
+    }
+
+    fn script(info: &mut SessionInfo, _: &mut Frame) -> Result<Flow, String> {
+        if info.script.is_empty() {
+            info.output.out("None");
+            return Ok(Flow::Stop);
+        }
+
+        info.output.out("\n");
+        for (i, token) in info.script.iter().enumerate() {
+            info.output.println(&format!("\t{}{}{}{}: {}", info.output.paint(BOLD), i, info.output.paint(DEFAULT), info.output.paint(GREEN), token));
+        }
+        Ok(Flow::Next)
+    }
+
+    fn pop_ident(info: &mut SessionInfo, _: &mut Frame) -> Result<Flow, String> {
+        if info.idents.pop().is_none() {
+            return Err(String::from("Nothing to pop."));
+        }
+        Ok(Flow::Next)
+    }
+
+    fn push_ident(info: &mut SessionInfo, _: &mut Frame) -> Result<Flow, String> {
+        let ident = info.stack.pop().unwrap();
+        info.idents.push(ident.to_string());
+        Ok(Flow::Next)
+    }
+
+    /// Prints {code} with `print`, or an empty message if there is none.
+    fn print_script(info: &mut SessionInfo, print: fn(&mut Output, &str)) {
+        if info.script.is_empty() {
+            print(&mut info.output, "");
+            return;
+        }
+
+        let msg = vec_to_line(&info.script);
+        print(&mut info.output, &msg);
+        info.script.clear();
+    }
+
+    /// Prints {code} with `print` unless output is suppressed.
+    fn print_optional(info: &mut SessionInfo, frame: &mut Frame, print: fn(&mut Output, &str)) {
+        if info.script.is_empty() {
+            info.script.push(" ".to_string());
+        }
+
+        if frame.verbose() {
+            let msg = vec_to_line(&info.script);
+            print(&mut info.output, &msg);
+        }
+        info.script.clear();
+    }
+
+    fn out_msg(info: &mut SessionInfo, _: &mut Frame) -> Result<Flow, String> {
+        print_script(info, Output::out);
+        Ok(Flow::Next)
+    }
+
+    fn warn_msg(info: &mut SessionInfo, _: &mut Frame) -> Result<Flow, String> {
+        if info.script.is_empty() {
+            info.output.error("");
+            return Ok(Flow::Next);
+        }
+        print_script(info, Output::warn);
+        Ok(Flow::Next)
+    }
+
+    fn err(info: &mut SessionInfo, _: &mut Frame) -> Result<Flow, String> {
+        print_script(info, Output::error);
+        Ok(Flow::Next)
+    }
+
+    fn oout(info: &mut SessionInfo, frame: &mut Frame) -> Result<Flow, String> {
+        print_optional(info, frame, Output::out);
+        Ok(Flow::Next)
+    }
+
+    fn owarn(info: &mut SessionInfo, frame: &mut Frame) -> Result<Flow, String> {
+        print_optional(info, frame, Output::warn);
+        Ok(Flow::Next)
+    }
+
+    fn oerr(info: &mut SessionInfo, frame: &mut Frame) -> Result<Flow, String> {
+        print_optional(info, frame, Output::error);
+        Ok(Flow::Next)
+    }
+
+    fn flush(info: &mut SessionInfo, _: &mut Frame) -> Result<Flow, String> {
+        print_script(info, Output::println);
+        Ok(Flow::Next)
+    }
 
-            "print" => {
-                if info.stack.is_empty() {
-                    error("Nothing to print.");
-                    break;
-                }
+    fn puts(info: &mut SessionInfo, _: &mut Frame) -> Result<Flow, String> {
+        let item = pop_ident_arg(info)?;
+        let (res, _) = get_string(&item, 1);
+        info.output.print(&res);
+        info.output.flush();
+        Ok(Flow::Next)
+    }
 
-                let item = info.stack.pop().unwrap();
-                println!("{item}");
+    fn pushch(info: &mut SessionInfo, _: &mut Frame) -> Result<Flow, String> {
+        let item = info.stack.pop().unwrap().to_string();
+        match info.script.pop() {
+            Some(mut last) => {
+                last.push_str(item.as_str());
+                info.script.push(last);
             }
+            None => info.script.push(item),
+        }
+        Ok(Flow::Next)
+    }
 
-            "script" => {
-                if info.script.is_empty() {
-                    out("None");
-                    break;
-                } 
+    fn formats(info: &mut SessionInfo, _: &mut Frame) -> Result<Flow, String> {
+        if info.idents.is_empty() {
+            return Err(String::from("Missing arguments required for fomatting."));
+        }
 
-                out("\n");
-                for i in 0..info.script.len() {
-                    println!("\t{}{}{}{}: {}", BOLD, i, DEFAULT, GREEN, info.script[i]);
-                }
-            }
+        let _target = info.idents.pop().unwrap();
+        Err(String::from("Not implemented."))
+    }
 
-            "clear_code" => {
-                info.script.clear();
-            }
+    fn def(info: &mut SessionInfo, frame: &mut Frame) -> Result<Flow, String> {
+        let key = pop_ident_arg(info)?;
+        info.check_binding(&key)?;
+        let value = info.stack.pop().unwrap();
 
-            "concatf" => {
-                if info.idents.is_empty() {
-                    error("Insufficient arguments!");
-                    break;
-                }
+        if frame.verbose() {
+            info.output.out(&format!("{} = {}", key, value));
+        }
 
-                let ident = info.idents.pop().unwrap();
-                info.script.push(ident);
-            }
+        info.variables.insert(key, value);
+        Ok(Flow::Next)
+    }
 
+    fn constant(info: &mut SessionInfo, frame: &mut Frame) -> Result<Flow, String> {
+        let key = pop_ident_arg(info)?;
+        // Declaring a constant again with the same value changes nothing, so exports can be reloaded.
+        if info.stack.last().is_some() && info.constants.get(&key) == info.stack.last() {
+            info.stack.pop();
+            return Ok(Flow::Next);
+        }
+        info.check_binding(&key)?;
+        if info.variables.contains_key(&key) {
+            return Err(format!("'{}' is already a variable; reset it before making it a constant.", key));
+        }
+        let value = info.stack.pop().unwrap();
 
-            "push_ident" => {
-                if info.stack.is_empty() {
-                    error("Insufficient arguments!");
-                    break;
-                }
+        if frame.verbose() {
+            info.output.out(&format!("{} = {}", key, value));
+        }
 
-                let ident = info.stack.pop().unwrap();
-                info.idents.push(ident.to_string());
+        info.constants.insert(key, value);
+        Ok(Flow::Next)
+    }
 
-            }
+    fn function(info: &mut SessionInfo, frame: &mut Frame) -> Result<Flow, String> {
+        let ident = pop_ident_arg(info)?;
+        if let Err(msg) = info.check_binding(&ident) {
+            info.script.clear();
+            return Err(msg);
+        }
+        let copy = copy_vec(&info.script);
 
-            "last" => {
-                info.stack.push(info.last);
-            }
+        if frame.verbose() {
+            info.output.out(&format!("{} = function {:?}", ident, copy));
+        }
 
-            "owarn" => {
-                if info.script.is_empty() {
-                    info.script.push(" ".to_string());
-                }
+        match frame.doc.take().or_else(|| info.pending_doc.take()) {
+            Some(text) => info.docs.insert(ident.to_owned(), text),
+            None => info.docs.remove(&ident),
+        };
+        info.functions.insert(ident, copy);
+        info.script.clear();
+        Ok(Flow::Next)
+    }
 
-                if !suppress_out && !supress_all {
-                    let msg = vec_to_line(&info.script);
-                    warn(&msg);
-                }
-                suppress_out = false;
-                info.script.clear();
-            },
+    fn namespace(info: &mut SessionInfo, _: &mut Frame) -> Result<Flow, String> {
+        let title = info.idents.pop().ok_or(String::from("Namespace requires a name."))?;
+        let mut namespace = Namespace::new(title.to_owned());
+        let mut namespace_session = SessionInfo::new();
+        namespace_session.quiet = info.quiet;
+        let outer: Vec<String> = info.namespaces.iter().map(|namespace| namespace.title.to_owned()).collect();
+        namespace_session.namespaces = std::mem::take(&mut info.namespaces);
+        namespace_session.aliases = info.aliases.clone();
+        namespace_session.opened = info.opened.clone();
+        std::mem::swap(&mut namespace_session.output, &mut info.output);
+        interpret_line(vec_to_line(&info.script), &mut namespace_session);
+        std::mem::swap(&mut namespace_session.output, &mut info.output);
+
+        // Namespaces defined inside the body become children of this one.
+        let (outside, inside): (Vec<Namespace>, Vec<Namespace>) = namespace_session.namespaces
+            .into_iter()
+            .partition(|namespace| outer.contains(&namespace.title));
+        info.namespaces = outside;
+        namespace.functions = namespace_session.functions;
+        namespace.docs = namespace_session.docs;
+        namespace.variables = namespace_session.variables;
+        namespace.constants = namespace_session.constants;
+        namespace.uses = namespace_session.opened.into_iter().filter(|used| !info.opened.contains(used)).collect();
+        namespace.adopt(inside);
+        info.add_namespace(namespace);
+        info.script.clear();
+        Ok(Flow::Next)
+    }
 
-            "oerr" => {
-                if info.script.is_empty() {
-                    info.script.push(" ".to_string());
-                }
+    fn use_namespace(info: &mut SessionInfo, frame: &mut Frame) -> Result<Flow, String> {
+        let name = frame.tokens.next().ok_or(String::from("use requires a namespace name."))?;
+        let (title, _) = info.resolve_namespace(&name)
+            .ok_or(format!("Unknown namespace '{}'.", name))?;
+        info.open_namespace(&title);
+        Ok(Flow::Next)
+    }
 
-                if !suppress_out && !supress_all {
-                    let msg = vec_to_line(&info.script);
-                    error(&msg);
-                }
-                suppress_out = false;
-                info.script.clear();
-            },
+    fn alias(info: &mut SessionInfo, frame: &mut Frame) -> Result<Flow, String> {
+        let name = pop_ident_arg(info)?;
+        let target = pop_ident_arg(info)?;
+        let (title, _) = info.resolve_namespace(&target)
+            .ok_or(format!("Unknown namespace '{}'.", target))?;
 
-            "oout" => {
-                if info.script.is_empty() {
-                    info.script.push(" ".to_string());
-                }
+        if frame.verbose() {
+            info.output.out(&format!("{} = namespace {}", name, title));
+        }
+        info.aliases.insert(name, title);
+        Ok(Flow::Next)
+    }
 
-                if !suppress_out && !supress_all {
-                    let msg = vec_to_line(&info.script);
-                    out(&msg);
-                }
-                suppress_out = false;
-                info.script.clear();
-            }
+    fn nothing(_: &mut SessionInfo, _: &mut Frame) -> Result<Flow, String> {
+        Ok(Flow::Next)
+    }
 
-            "pop_ident" => {
-                if info.idents.is_empty() {
-                    error("Nothing to pop.");
-                    break;
+    fn load(info: &mut SessionInfo, frame: &mut Frame) -> Result<Flow, String> {
+        let filepath = pop_ident_arg(info)?;
+        let res = load_file(filepath.as_str(), info).is_ok();
+
+        if frame.verbose() && res {
+            info.output.out(&format!("Successfully loaded file '{}'.", filepath));
+        }
+        Ok(Flow::Next)
+    }
+
+    fn import_module(info: &mut SessionInfo, frame: &mut Frame) -> Result<Flow, String> {
+        // At the end of a line, as in std's `import!`, the name comes from the identifier stack.
+        let name = frame.tokens.next()
+            .or_else(|| info.idents.pop())
+            .ok_or(String::from("import requires a module name."))?;
+        match import(&name, info)? {
+            Imported::Loaded => {
+                if frame.verbose() {
+                    info.output.out(&format!("Imported module '{}'.", name));
                 }
-                info.idents.pop();
             }
+            Imported::AlreadyLoaded => (),
+            Imported::Failed => return Ok(Flow::Stop),
+        }
+        Ok(Flow::Next)
+    }
 
-            "identifiers" => {
-                if info.idents.is_empty() {
-                    out("None");
-                    break;
+    fn save(info: &mut SessionInfo, frame: &mut Frame) -> Result<Flow, String> {
+        let filepath = pop_ident_arg(info)?;
+        if save_session(info, &filepath).is_err() {
+            return Err(format!("Could not write file '{}'.", filepath));
+        }
+
+        if frame.verbose() {
+            info.output.out(&format!("Session saved to '{}'.", filepath));
+        }
+        Ok(Flow::Next)
+    }
+
+    fn restore(info: &mut SessionInfo, frame: &mut Frame) -> Result<Flow, String> {
+        let filepath = pop_ident_arg(info)?;
+        restore_session(info, &filepath)?;
+
+        if frame.verbose() {
+            info.output.out(&format!("Session restored from '{}'.", filepath));
+        }
+        Ok(Flow::Next)
+    }
+
+    fn export(info: &mut SessionInfo, frame: &mut Frame) -> Result<Flow, String> {
+        let filepath = pop_ident_arg(info)?;
+        if export_session(info, &filepath).is_err() {
+            return Err(format!("Could not write file '{}'.", filepath));
+        }
+
+        if frame.verbose() {
+            info.output.out(&format!("Session exported to '{}'.", filepath));
+        }
+        Ok(Flow::Next)
+    }
+
+    fn reset(info: &mut SessionInfo, frame: &mut Frame) -> Result<Flow, String> {
+        if !info.script.is_empty() {
+            let verbose = frame.verbose();
+            for ident in std::mem::take(&mut info.script) {
+                if info.constant(&ident).is_some() {
+                    fail(info, &format!("'{}' is a constant and cannot be reset.", ident));
+                    continue;
                 }
 
-                out("\n");
-                for i in 0..info.idents.len() {
-                    println!("\t{}{}{}",
-                    UNDERLINE, info.idents[i], NO_UNDERLINE);
+                let variable = info.variables.remove(&ident).is_some();
+                if variable && verbose {
+                    info.output.out(&format!("Variable '{}' removed.", ident));
+                }
+                let function = info.functions.remove(&ident).is_some();
+                if function {
+                    info.docs.remove(&ident);
+                    if verbose {
+                        info.output.out(&format!("Function '{}' removed.", ident));
+                    }
+                }
+                if !variable && !function {
+                    fail(info, &format!("Could not find identifier '{}'.", ident));
                 }
-                println!("");
             }
+            return Ok(Flow::Next);
+        }
+
+        info.reset();
+        if frame.verbose() {
+            info.output.out("Variables and functions reset.");
+        }
+        Ok(Flow::Next)
+    }
 
+    fn concatf(info: &mut SessionInfo, _: &mut Frame) -> Result<Flow, String> {
+        let ident = pop_ident_arg(info)?;
+        info.script.push(ident);
+        Ok(Flow::Next)
+    }
 
-            "+" => {
-                let rhs = info.stack.pop();
-                let lhs = info.stack.pop();
+    fn clear(info: &mut SessionInfo, _: &mut Frame) -> Result<Flow, String> {
+        info.output.print("\x1b[2J");
+        info.output.flush();
+        Ok(Flow::Next)
+    }
 
-                if rhs.is_none() || lhs.is_none() {
-                    error("Insufficient arguments!");
-                    break;
-                }
+    fn ansi(info: &mut SessionInfo, _: &mut Frame) -> Result<Flow, String> {
+        if info.script.is_empty() {
+            return Err(String::from("Insufficient arguments!"));
+        }
 
-                let right = rhs.unwrap();
-                let left = lhs.unwrap();
+        let cmd = vec_to_line(&info.script);
+        info.output.print(&format!("\x1b{}", cmd));
+        info.output.flush();
+        info.script.clear();
+        Ok(Flow::Next)
+    }
 
-                info.stack.push(left + right);
+    fn help(info: &mut SessionInfo, frame: &mut Frame) -> Result<Flow, String> {
+        match frame.tokens.next() {
+            Some(word) => {
+                if !print_word_help(&word, info) {
+                    return Err(format!("No help found for '{}'.", word));
+                }
             }
+            None => print_help(&mut info.output),
+        }
+        Ok(Flow::Next)
+    }
+
+    fn complete(info: &mut SessionInfo, frame: &mut Frame) -> Result<Flow, String> {
+        let prefix = frame.tokens.next().unwrap_or_default();
+        let names = completions(&prefix, info);
+        if names.is_empty() {
+            info.output.out("None");
+        } else {
+            info.output.out(&names.join(" "));
+        }
+        Ok(Flow::Next)
+    }
 
-            "-" => {
-                let rhs = info.stack.pop();
-                let lhs = info.stack.pop();
+    fn credits(info: &mut SessionInfo, _: &mut Frame) -> Result<Flow, String> {
+        info.output.out("Codebook created by Amelia Johnson.");
+        Ok(Flow::Next)
+    }
 
-                if rhs.is_none() || lhs.is_none() {
-                    error("Insufficient arguments!");
-                    break;
-                }
+    fn about(info: &mut SessionInfo, _: &mut Frame) -> Result<Flow, String> {
+        info.output.out("Not yet implemented.");
+        Ok(Flow::Next)
+    }
 
-                let right = rhs.unwrap();
-                let left = lhs.unwrap();
+    fn clear_code(info: &mut SessionInfo, _: &mut Frame) -> Result<Flow, String> {
+        info.script.clear();
+        Ok(Flow::Next)
+    }
 
-                info.stack.push(left - right);
+    fn rpn(info: &mut SessionInfo, frame: &mut Frame) -> Result<Flow, String> {
+        info.persistent = !info.persistent;
+        if frame.verbose() {
+            if info.persistent {
+                info.output.out("RPN mode on; the stack persists between lines.");
+            } else {
+                info.output.out("RPN mode off.");
             }
+        }
+        Ok(Flow::Next)
+    }
 
-            "*" => {
-                let rhs = info.stack.pop();
-                let lhs = info.stack.pop();
-
-                if rhs.is_none() || lhs.is_none() {
-                    error("Insufficient arguments!");
-                    break;
-                }
+    fn panel(info: &mut SessionInfo, frame: &mut Frame) -> Result<Flow, String> {
+        info.panel = !info.panel;
+        if frame.verbose() {
+            if info.panel {
+                info.output.out("Stack panel on.");
+            } else {
+                info.output.out("Stack panel off.");
+            }
+        }
+        Ok(Flow::Next)
+    }
 
-                let right = rhs.unwrap();
-                let left = lhs.unwrap();
+    fn theme(info: &mut SessionInfo, frame: &mut Frame) -> Result<Flow, String> {
+        let key = pop_ident_arg(info)?;
+        let value = vec_to_line(&std::mem::take(&mut info.script));
+        // A quoted value keeps its spaces, e.g. { "> " } prompt theme.
+        let value = match value.strip_prefix('"') {
+            Some(_) => get_string(&value, 1).0,
+            None => value,
+        };
+        info.output.theme.set(&key, &value)?;
+
+        if frame.verbose() {
+            info.output.out(&format!("Theme setting '{}' changed.", key));
+        }
+        Ok(Flow::Next)
+    }
 
-                info.stack.push(left * right);
+    fn color(info: &mut SessionInfo, frame: &mut Frame) -> Result<Flow, String> {
+        if info.output.color {
+            info.output.set_color(DEFAULT);
+        }
+        info.output.color = !info.output.color;
+        if frame.verbose() {
+            if info.output.color {
+                info.output.out("Colors on.");
+            } else {
+                info.output.out("Colors off.");
             }
+        }
+        Ok(Flow::Next)
+    }
+
+    fn undo(info: &mut SessionInfo, frame: &mut Frame) -> Result<Flow, String> {
+        info.history.pending = None;
+        let current = info.snapshot();
+        let previous = info.history.undo(current).ok_or(String::from("Nothing to undo."))?;
+        info.restore(previous);
+        if frame.verbose() {
+            info.output.out("Undone.");
+        }
+        Ok(Flow::Next)
+    }
 
-            "/" => {
-                let rhs = info.stack.pop();
-                let lhs = info.stack.pop();
+    fn redo(info: &mut SessionInfo, frame: &mut Frame) -> Result<Flow, String> {
+        info.history.pending = None;
+        let current = info.snapshot();
+        let next = info.history.redo(current).ok_or(String::from("Nothing to redo."))?;
+        info.restore(next);
+        if frame.verbose() {
+            info.output.out("Redone.");
+        }
+        Ok(Flow::Next)
+    }
 
-                if rhs.is_none() || lhs.is_none() {
-                    error("Insufficient arguments!");
-                    break;
+    fn if_block(info: &mut SessionInfo, _: &mut Frame) -> Result<Flow, String> {
+        let res = info.stack.pop().unwrap();
+        if res == 1.0 {
+            let copy = copy_vec(&info.script);
+            info.script.clear();
+            interpret_vec(&copy, info);
+            info.bool_val = Some(1.0);
+        } else {
+            info.bool_val = Some(0.0);
+        }
+        info.script.clear();
+        Ok(Flow::Next)
+    }
+
+    fn elif_block(info: &mut SessionInfo, _: &mut Frame) -> Result<Flow, String> {
+        let cond = info.stack.pop().unwrap();
+        match info.bool_val {
+            Some(0.0) => {
+                if cond == 1.0 {
+                    let exec = copy_vec(&info.script);
+                    info.script.clear();
+                    info.bool_val = Some(1.0);
+                    interpret_vec(&exec, info);
+                } else {
+                    info.bool_val = Some(0.0);
                 }
+            }
+            Some(_) => info.bool_val = None,
+            None => {
+                info.script.clear();
+                return Err(String::from("elif block missing if/elif block!"));
+            }
+        }
+        info.script.clear();
+        Ok(Flow::Next)
+    }
 
-                let right = rhs.unwrap();
-                let left = lhs.unwrap();
+    fn else_block(info: &mut SessionInfo, _: &mut Frame) -> Result<Flow, String> {
+        match info.bool_val {
+            Some(0.0) => {
+                let exec = copy_vec(&info.script);
+                info.script.clear();
+                interpret_vec(&exec, info);
+            }
+            Some(_) => info.bool_val = None,
+            None => (),
+        }
+        info.script.clear();
+        Ok(Flow::Next)
+    }
 
-                info.stack.push(left / right);
+    fn times(info: &mut SessionInfo, _: &mut Frame) -> Result<Flow, String> {
+        let times = info.stack.pop().unwrap();
+        let copy = copy_vec(&info.script);
+        info.script.clear();
+        for _ in 0..times as i32 {
+            if info.exit_code.is_some() {
+                break;
             }
+            interpret_vec(&copy, info);
+        }
+        Ok(Flow::Next)
+    }
+
+    fn stop(_: &mut SessionInfo, _: &mut Frame) -> Result<Flow, String> {
+        Ok(Flow::Stop)
+    }
 
-            "%" => {
-                let rhs = info.stack.pop();
-                let lhs = info.stack.pop();
+    fn exit_with(info: &mut SessionInfo, _: &mut Frame) -> Result<Flow, String> {
+        let code = info.stack.pop().unwrap_or(0.0);
+        info.exit_code = Some(code as i32);
+        Ok(Flow::Stop)
+    }
 
-                if rhs.is_none() || lhs.is_none() {
-                    error("Insufficient arguments!");
-                    break;
-                }
+    fn suppress(_: &mut SessionInfo, frame: &mut Frame) -> Result<Flow, String> {
+        frame.suppress_out = true;
+        Ok(Flow::Next)
+    }
 
-                let right = rhs.unwrap();
-                let left = lhs.unwrap();
+    fn suppress_all(_: &mut SessionInfo, frame: &mut Frame) -> Result<Flow, String> {
+        frame.suppress_all = true;
+        Ok(Flow::Next)
+    }
 
-                info.stack.push(left % right);
-            }
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use crate::interpreter::test_session as session;
+
+        #[test]
+        fn trigonometry_follows_the_angle_mode() {
+            let mut info = session();
+            interpret_line(String::from("-sall deg 90 sin 180 sin 1 asin"), &mut info);
+            assert_eq!(info.error, None);
+            assert_eq!(info.stack, vec![1.0, 0.0, 90.0]);
+
+            let mut info = session();
+            interpret_line(String::from("-sall grad 100 sin rad 0 cos"), &mut info);
+            assert_eq!(info.stack, vec![1.0, 1.0]);
+        }
 
-            "**" => {
-                let rhs = info.stack.pop();
-                let lhs = info.stack.pop();
+        #[test]
+        fn huge_counts_are_reported() {
+            let mut info = session();
+            interpret_line(String::from("-sall 1 2 1e30 linreg"), &mut info);
+            assert_eq!(info.error.as_deref(), Some("'linreg' needs 2000000000000000000000000000000 items, but there are only 2."));
+            assert_eq!(info.stack, vec![1.0, 2.0, 1e30]);
 
-                if rhs.is_none() || lhs.is_none() {
-                    error("Insufficient arguments!");
-                    break;
-                }
+            let mut info = session();
+            interpret_line(String::from("-sall 1 2 3 mean"), &mut info);
+            assert_eq!(info.error.as_deref(), Some("'mean' needs 3 items, but there are only 2."));
+        }
 
-                let right = rhs.unwrap();
-                let left = lhs.unwrap();
+        #[test]
+        fn constants_can_be_used_in_definitions() {
+            let mut info = session();
+            interpret_line(String::from("-sall r 2 pi * def t 2 PI * def"), &mut info);
+            assert_eq!(info.error, None);
+            assert_eq!(info.variables.get("r"), Some(&std::f32::consts::TAU));
+            assert_eq!(info.variables.get("t"), Some(&std::f32::consts::TAU));
+            assert!(info.stack.is_empty());
+            assert!(info.idents.is_empty());
+        }
 
-                info.stack.push(left.powf(right));
+        #[test]
+        fn constant_before_def_is_not_redefined() {
+            for (line, error) in [
+                ("-sall 5 pi def", "'pi' is a built-in constant and cannot be redefined."),
+                ("-sall 5 PI def", "'PI' is a constant of 'std' and cannot be redefined."),
+                ("-sall c 1 const 5 c def", "'c' is a constant and cannot be redefined."),
+            ] {
+                let mut info = session();
+                interpret_line(String::from(line), &mut info);
+                assert_eq!(info.error.as_deref(), Some(error), "{}", line);
             }
+        }
 
-            "++" => {
-                if info.stack.is_empty() {
-                    error("Insufficient arguments!");
-                    break;
-                }
+        #[test]
+        fn constants_of_opened_namespaces_cannot_be_shadowed() {
+            let mut info = session();
+            interpret_line(String::from("-sall 3 PI def"), &mut info);
+            assert_eq!(info.error.as_deref(), Some("'PI' is a constant of 'std' and cannot be redefined."));
+            assert!(!info.variables.contains_key("PI"));
+
+            let mut info = session();
+            interpret_line(String::from("-sall { 1 k const } consts namespace k 2 def use consts 3 k def"), &mut info);
+            assert_eq!(info.error.as_deref(), Some("'k' is a constant of 'consts' and cannot be redefined."));
+            assert_eq!(info.variables.get("k"), Some(&2.0));
+        }
 
-                let num = info.stack.pop().unwrap();
-                info.stack.push(num + 1.0);
-            }
+        #[test]
+        fn builtin_names_cannot_be_registered() {
+            let mut namespace = Namespace::new(String::from("parts"));
+            assert_eq!(namespace.register("sqrt", 1, |args| Ok(vec![args[0]])),
+                Err(String::from("'sqrt' is a built-in word and cannot be redefined.")));
+            assert_eq!(namespace.register("pi", 0, |_| Ok(vec![3.0])),
+                Err(String::from("'pi' is a built-in constant and cannot be redefined.")));
+            assert_eq!(namespace.register("stock", 1, |args| Ok(vec![args[0]])), Ok(()));
+            assert!(namespace.natives.contains_key("stock"));
+        }
 
-            "--" => {
-                if info.stack.is_empty() {
-                    error("Insufficient arguments!");
-                    break;
-                }
+        #[test]
+        fn builtin_names_cannot_be_redefined() {
+            let mut info = session();
+            interpret_line(String::from("-sall { 2 * } mean fn"), &mut info);
+            assert_eq!(info.error.as_deref(), Some("'mean' is a built-in word and cannot be redefined."));
+            assert!(info.functions.is_empty());
+            assert!(info.script.is_empty());
+
+            let mut info = session();
+            interpret_line(String::from("-sall hex 16 def"), &mut info);
+            assert!(info.error.is_some());
+            assert!(!info.variables.contains_key("hex"));
+        }
 
-                let num = info.stack.pop().unwrap();
-                info.stack.push(num - 1.0);
-            }
+        #[test]
+        fn only_std_is_opened() {
+            let mut info = session();
+            interpret_line(String::from("-sall { { 100 + } sum! fn } mine namespace 1 2 sum!"), &mut info);
+            assert_eq!(info.stack, vec![3.0]);
+            assert_eq!(info.opened, vec![String::from("std")]);
+        }
 
-            ">>" => {
-                if info.stack.len() < 2 {
-                    error("Insufficient arguments!");
-                    break;
-                }
+        #[test]
+        fn namespace_sees_open_namespaces() {
+            let mut info = session();
+            interpret_line(String::from("-sall { x 16 sqrt! def } ns namespace ns::x"), &mut info);
+            assert_eq!(info.error, None);
+            assert_eq!(info.stack, vec![4.0]);
+            let namespace = info.namespace("ns").unwrap();
+            assert!(!namespace.variables.contains_key("sqrt!"));
+            assert!(namespace.uses.is_empty());
+        }
 
-                let rhs = info.stack.pop().unwrap() as i32;
-                let lhs = info.stack.pop().unwrap() as i32;
+        #[test]
+        fn use_opens_a_namespace() {
+            let mut info = session();
+            interpret_line(String::from("-sall { { 100 + } plus! fn } mine namespace use mine 1 plus!"), &mut info);
+            assert_eq!(info.error, None);
+            assert_eq!(info.stack, vec![101.0]);
 
-                info.stack.push((lhs >> rhs) as f32);
-            }
+            interpret_line(String::from("-sall { { 100 + } sum! fn } more namespace use more 1 2 sum!"), &mut info);
+            assert_eq!(info.error.as_deref(), Some("'sum!' is ambiguous; use std::sum! or more::sum!."));
+        }
+    }
 
-            "<" => {
-                if info.stack.len() < 2 {
-                    error("Insufficient arguments!");
-                    break;
-                }
+}
 
-                let rhs = info.stack.pop().unwrap();
-                let lhs = info.stack.pop().unwrap();
+pub mod storage {
+    use std::collections::HashMap;
+    use std::fs;
+    use std::io;
+    use super::interpet::{get_string, tokenize, STDLIB_NAMESPACE};
+    use super::namespaces::Namespace;
+    use super::session::SessionInfo;
+
+    pub const SESSION_HEADER: &str = "codebook-session 1";
+    const INLINE_BLOCK_LIMIT: usize = 6;
+
+    /// Re-escapes a string literal token so it tokenizes back to itself.
+    pub fn escape_token(token: &str) -> String {
+        if token.len() < 2 || !token.starts_with('"') || !token.ends_with('"') {
+            return token.to_owned();
+        }
 
-                if lhs < rhs {
-                    info.stack.push(1.0);
-                } else {
-                    info.stack.push(0.0);
-                }
+        let mut res = String::from('"');
+        for ch in token[1..token.len() - 1].chars() {
+            match ch {
+                '\\' => res.push_str("\\\\"),
+                '"' => res.push_str("\\\""),
+                '\n' => res.push_str("\\n"),
+                '\t' => res.push_str("\\t"),
+                _ => res.push(ch),
             }
+        }
+        res.push('"');
+        res
+    }
 
-            ">" => {
-                if info.stack.len() < 2 {
-                    error("Insufficient arguments!");
-                    break;
-                }
+    fn quote(text: &str) -> String {
+        escape_token(&format!("\"{}\"", text))
+    }
 
-                let rhs = info.stack.pop().unwrap();
-                let lhs = info.stack.pop().unwrap();
+    fn unquote(text: &str) -> String {
+        get_string(text.trim(), 1).0
+    }
 
-                if lhs > rhs {
-                    info.stack.push(1.0);
-                } else {
-                    info.stack.push(0.0);
+    pub fn function_source(body: &[String]) -> String {
+        let tokens: Vec<String> = body.iter().map(|token| escape_token(token)).collect();
+        tokens.join(" ")
+    }
+
+    fn sorted<V>(map: &HashMap<String, V>) -> Vec<(&String, &V)> {
+        let mut entries: Vec<(&String, &V)> = map.iter().collect();
+        entries.sort_by(|a, b| a.0.cmp(b.0));
+        entries
+    }
+
+    pub fn serialize_session(info: &SessionInfo) -> String {
+        let mut lines = vec![SESSION_HEADER.to_owned()];
+        for (name, body) in sorted(&info.functions) {
+            lines.push(format!("function {} {}", name, function_source(body)));
+            if let Some(doc) = info.docs.get(name) {
+                lines.push(format!("doc {} {}", name, quote(doc)));
+            }
+        }
+        for (name, value) in sorted(&info.variables) {
+            lines.push(format!("variable {} {}", name, value));
+        }
+        for (name, value) in sorted(&info.constants) {
+            lines.push(format!("constant {} {}", name, value));
+        }
+        for (path, namespace) in info.all_namespaces() {
+            lines.push(format!("namespace {}", path));
+            for (name, body) in sorted(&namespace.functions) {
+                lines.push(format!("namespace_function {} {} {}",
+                    path, name, function_source(body)));
+                if let Some(doc) = namespace.docs.get(name) {
+                    lines.push(format!("namespace_doc {} {} {}", path, name, quote(doc)));
                 }
             }
+            for (name, value) in sorted(&namespace.variables) {
+                lines.push(format!("namespace_variable {} {} {}", path, name, value));
+            }
+            for (name, value) in sorted(&namespace.constants) {
+                lines.push(format!("namespace_constant {} {} {}", path, name, value));
+            }
+            for used in &namespace.uses {
+                lines.push(format!("namespace_use {} {}", path, used));
+            }
+        }
+        for title in &info.opened {
+            lines.push(format!("open {}", title));
+        }
+        for (name, title) in sorted(&info.aliases) {
+            lines.push(format!("alias {} {}", name, title));
+        }
+        let stack: Vec<String> = info.stack.iter().map(|item| item.to_string()).collect();
+        lines.push(format!("stack {}", stack.join(" ")).trim_end().to_owned());
+        lines.push(format!("last {}", info.last));
+        lines.push(String::new());
+        lines.join("\n")
+    }
 
-            "<=" => {
-                if info.stack.len() < 2 {
-                    error("Insufficient arguments!");
-                    break;
-                }
+    pub fn save_session(info: &SessionInfo, filepath: &str) -> io::Result<()> {
+        fs::write(filepath, serialize_session(info))
+    }
 
-                let rhs = info.stack.pop().unwrap();
-                let lhs = info.stack.pop().unwrap();
+    fn push_line(lines: &mut Vec<String>, current: &mut Vec<String>, depth: usize) {
+        if !current.is_empty() {
+            lines.push(format!("{}{}", "    ".repeat(depth), current.join(" ")));
+            current.clear();
+        }
+    }
 
-                if lhs <= rhs {
-                    info.stack.push(1.0);
-                } else {
-                    info.stack.push(0.0);
-                }
+    /// Length of a `{ ... }` block starting at `start` if it is short enough to stay on one line.
+    fn inline_block(body: &[String], start: usize) -> Option<usize> {
+        for (offset, token) in body[start + 1..].iter().enumerate() {
+            match token.as_str() {
+                "{" => return None,
+                "}" => return Some(offset + 2),
+                _ if offset >= INLINE_BLOCK_LIMIT => return None,
+                _ => (),
             }
+        }
+        None
+    }
 
-            ">=" => {
-                if info.stack.len() < 2 {
-                    error("Insufficient arguments!");
-                    break;
-                }
-
-                let rhs = info.stack.pop().unwrap();
-                let lhs = info.stack.pop().unwrap();
+    /// Pretty-prints a token list, breaking lines and indenting at every `{` and `}`.
+    pub fn pretty_lines(body: &[String], depth: usize, lines: &mut Vec<String>) {
+        let mut current: Vec<String> = Vec::new();
+        let mut depth = depth;
+        let mut index = 0;
+        while index < body.len() {
+            let token = &body[index];
+            index += 1;
+            match token.as_str() {
+                "{" => {
+                    if let Some(len) = inline_block(body, index - 1) {
+                        let block = &body[index - 1..index - 1 + len];
+                        current.push(function_source(block));
+                        index += len - 1;
+                        continue;
+                    }
 
-                if lhs >= rhs {
-                    info.stack.push(1.0);
-                } else {
-                    info.stack.push(0.0);
+                    current.push(token.to_owned());
+                    push_line(lines, &mut current, depth);
+                    depth += 1;
                 }
-            }
 
-            "<<" => {
-                if info.stack.len() < 2 {
-                    error("Insufficient arguments!");
-                    break;
+                "}" => {
+                    push_line(lines, &mut current, depth);
+                    depth = depth.saturating_sub(1);
+                    current.push(token.to_owned());
                 }
 
-                let rhs = info.stack.pop().unwrap() as i32;
-                let lhs = info.stack.pop().unwrap() as i32;
+                _ => current.push(escape_token(token)),
+            }
+        }
+        push_line(lines, &mut current, depth);
+    }
+
+    fn export_function(name: &str, body: &[String], doc: Option<&String>, depth: usize, lines: &mut Vec<String>) {
+        let indent = "    ".repeat(depth);
+        if let Some(doc) = doc {
+            for line in doc.lines() {
+                lines.push(format!("{}## {}", indent, line));
+            }
+        }
+        lines.push(format!("{}{{", indent));
+        pretty_lines(body, depth + 1, lines);
+        lines.push(format!("{}}} {} fn", indent, name));
+    }
 
-                info.stack.push((lhs << rhs) as f32);
+    /// The name `used` had inside the body of the namespace at `path`, where the namespace
+    /// and its parents did not exist yet.
+    fn local_name<'a>(used: &'a str, path: &str) -> &'a str {
+        let mut path = path;
+        loop {
+            if let Some(rest) = used.strip_prefix(path).and_then(|rest| rest.strip_prefix("::")) {
+                return rest;
             }
+            match path.rsplit_once("::") {
+                Some((parent, _)) => path = parent,
+                None => return used,
+            }
+        }
+    }
 
-            "|" => {
-                if info.stack.len() < 2 {
-                    error("Insufficient arguments!");
-                    break;
-                }
+    fn export_namespace(namespace: &Namespace, path: &str, depth: usize, lines: &mut Vec<String>) {
+        let indent = "    ".repeat(depth);
+        lines.push(format!("{}{{", indent));
+        lines.push(format!("{}    -sall", indent));
+        // Children first, so `use` lines naming them find them.
+        for child in namespace.children.iter().filter(|child| child.has_source()) {
+            export_namespace(child, &format!("{}::{}", path, child.title), depth + 1, lines);
+        }
+        for used in &namespace.uses {
+            lines.push(format!("{}    use {}", indent, local_name(used, path)));
+        }
+        for (name, value) in sorted(&namespace.constants) {
+            lines.push(format!("{}    {} {} const", indent, value, name));
+        }
+        for (name, value) in sorted(&namespace.variables) {
+            lines.push(format!("{}    {} {} def", indent, value, name));
+        }
+        for (name, body) in sorted(&namespace.functions) {
+            lines.push(String::new());
+            export_function(name, body, namespace.docs.get(name), depth + 1, lines);
+        }
+        lines.push(format!("{}}} {} namespace", indent, namespace.title));
+    }
 
-                let rhs = info.stack.pop().unwrap() as i32;
-                let lhs = info.stack.pop().unwrap() as i32;
+    /// Writes every definition in the session as CSL source that can be loaded with `load_file`.
+    /// The bundled std namespace is left out, since every session loads it at startup.
+    pub fn export_source(info: &SessionInfo) -> String {
+        let mut lines = vec![String::from("-sall"), String::new()];
+        let exported = |namespace: &&Namespace| namespace.has_source() && namespace.title != STDLIB_NAMESPACE;
+        for namespace in info.namespaces.iter().filter(exported) {
+            export_namespace(namespace, &namespace.title, 0, &mut lines);
+            lines.push(String::new());
+        }
+        let root = |title: &String| info.namespace(title.split("::").next().unwrap_or(title)).is_some_and(|namespace| exported(&namespace));
+        for title in info.opened.iter().filter(|title| root(title)) {
+            lines.push(format!("use {}", title));
+        }
+        for (name, title) in sorted(&info.aliases) {
+            lines.push(format!("{} {} alias", title, name));
+        }
 
-                info.stack.push((lhs | rhs) as f32);
-            }
+        for (name, value) in sorted(&info.constants) {
+            lines.push(format!("{} {} const", value, name));
+        }
+        for (name, value) in sorted(&info.variables) {
+            lines.push(format!("{} {} def", value, name));
+        }
 
-            "&" => {
-                if info.stack.len() < 2 {
-                    error("Insufficient arguments!");
-                    break;
-                }
+        for (name, body) in sorted(&info.functions) {
+            lines.push(String::new());
+            export_function(name, body, info.docs.get(name), 0, &mut lines);
+        }
+        lines.push(String::new());
+        lines.join("\n")
+    }
 
-                let rhs = info.stack.pop().unwrap() as i32;
-                let lhs = info.stack.pop().unwrap() as i32;
+    pub fn export_session(info: &SessionInfo, filepath: &str) -> io::Result<()> {
+        fs::write(filepath, export_source(info))
+    }
 
-                info.stack.push((lhs & rhs) as f32);
-            }
+    fn parse_number(word: Option<&str>, line_no: usize) -> Result<f32, String> {
+        word.and_then(|w| w.parse::<f32>().ok())
+            .ok_or(format!("Line {}: expected a number.", line_no))
+    }
 
-            "^" => {
-                if info.stack.len() < 2 {
-                    error("Insufficient arguments!");
-                    break;
-                }
+    fn split_word(rest: &str) -> (&str, &str) {
+        match rest.split_once(' ') {
+            Some((word, tail)) => (word, tail),
+            None => (rest, ""),
+        }
+    }
 
-                let rhs = info.stack.pop().unwrap() as i32;
-                let lhs = info.stack.pop().unwrap() as i32;
+    /// Moves namespaces titled with a `parent::child` path into their parents.
+    fn nest(mut flat: Vec<Namespace>) -> Result<Vec<Namespace>, String> {
+        // Deepest first, so every child is complete before it is moved.
+        flat.sort_by_key(|namespace| std::cmp::Reverse(namespace.title.matches("::").count()));
+        let mut roots = Vec::new();
+        while !flat.is_empty() {
+            let mut namespace = flat.remove(0);
+            let Some((parent, title)) = namespace.title.rsplit_once("::") else {
+                roots.push(namespace);
+                continue;
+            };
+            let (parent, title) = (parent.to_owned(), title.to_owned());
+            namespace.title = title;
+            flat.iter_mut()
+                .find(|candidate| candidate.title == parent)
+                .ok_or(format!("Namespace '{}' is missing.", parent))?
+                .children.push(namespace);
+        }
+        Ok(roots)
+    }
 
-                info.stack.push((lhs ^ rhs) as f32);
-            }
+    /// Reads a saved session and replaces the definitions and stack of `info` with it.
+    /// Nothing is changed if the file cannot be parsed.
+    pub fn restore_session(info: &mut SessionInfo, filepath: &str) -> Result<(), String> {
+        let contents = fs::read_to_string(filepath)
+            .map_err(|_| format!("Could not read file '{}'.", filepath))?;
+        let mut lines = contents.lines().enumerate();
+        let header = lines.next().map(|(_, l)| l.trim());
+        if header != Some(SESSION_HEADER) {
+            return Err(format!("'{}' is not a saved session.", filepath));
+        }
 
-            "break" => {
-                break;
+        let mut functions = HashMap::new();
+        let mut docs = HashMap::new();
+        let mut variables = HashMap::new();
+        let mut constants = HashMap::new();
+        let mut namespaces: Vec<Namespace> = Vec::new();
+        let mut stack = Vec::new();
+        let mut last = 0.0;
+        let mut opened: Vec<String> = Vec::new();
+        let mut aliases = HashMap::new();
+
+        for (index, line) in lines {
+            let line_no = index + 1;
+            if line.trim().is_empty() {
+                continue;
             }
 
-            "err" => {
-                if info.script.is_empty() {
-                    error("");
-                    continue;
+            let (kind, rest) = split_word(line);
+            match kind {
+                "function" => {
+                    let (name, body) = split_word(rest);
+                    functions.insert(name.to_owned(), tokenize(body));
+                }
+
+                "doc" => {
+                    let (name, text) = split_word(rest);
+                    docs.insert(name.to_owned(), unquote(text));
+                }
+
+                "variable" | "constant" => {
+                    let mut words = rest.split_whitespace();
+                    let name = words.next()
+                        .ok_or(format!("Line {}: expected a name.", line_no))?;
+                    let value = parse_number(words.next(), line_no)?;
+                    if kind == "variable" {
+                        variables.insert(name.to_owned(), value);
+                    } else {
+                        constants.insert(name.to_owned(), value);
+                    }
                 }
 
-                let msg = vec_to_line(&info.script);
-                error(&msg);
-                info.script.clear();
-            }
+                "namespace" => namespaces.push(Namespace::new(rest.trim().to_owned())),
+
+                "namespace_function" => {
+                    let (title, rest) = split_word(rest);
+                    let (name, body) = split_word(rest);
+                    let namespace = namespaces.iter_mut()
+                        .find(|namespace| namespace.title == title)
+                        .ok_or(format!("Line {}: unknown namespace '{}'.", line_no, title))?;
+                    namespace.functions.insert(name.to_owned(), tokenize(body));
+                }
+
+                "namespace_doc" => {
+                    let (title, rest) = split_word(rest);
+                    let (name, text) = split_word(rest);
+                    let namespace = namespaces.iter_mut()
+                        .find(|namespace| namespace.title == title)
+                        .ok_or(format!("Line {}: unknown namespace '{}'.", line_no, title))?;
+                    namespace.docs.insert(name.to_owned(), unquote(text));
+                }
+
+                "namespace_variable" | "namespace_constant" => {
+                    let mut words = rest.split_whitespace();
+                    let title = words.next().unwrap_or_default();
+                    let name = words.next()
+                        .ok_or(format!("Line {}: expected a name.", line_no))?;
+                    let value = parse_number(words.next(), line_no)?;
+                    let namespace = namespaces.iter_mut()
+                        .find(|namespace| namespace.title == title)
+                        .ok_or(format!("Line {}: unknown namespace '{}'.", line_no, title))?;
+                    if kind == "namespace_variable" {
+                        namespace.variables.insert(name.to_owned(), value);
+                    } else {
+                        namespace.constants.insert(name.to_owned(), value);
+                    }
+                }
 
-            "constants" => {
-                if info.constants.len() == 0 {
-                    out("None");
-                    continue;
+                "namespace_use" => {
+                    let (title, used) = split_word(rest);
+                    let namespace = namespaces.iter_mut()
+                        .find(|namespace| namespace.title == title)
+                        .ok_or(format!("Line {}: unknown namespace '{}'.", line_no, title))?;
+                    namespace.uses.push(used.trim().to_owned());
                 }
 
-                out("\n");
-                for variable in info.constants.keys() {
-                    println!("\t{}{}{}{} = {}", 
-                    BOLD,
-                    variable, 
-                    DEFAULT,
-                    GREEN,
-                    info.constants.get(variable).unwrap());
+                "open" => opened.push(rest.trim().to_owned()),
+
+                "alias" => {
+                    let (name, title) = split_word(rest);
+                    aliases.insert(name.to_owned(), title.trim().to_owned());
                 }
-            }
 
-            "warn" => {
-                if info.script.is_empty() {
-                    error("");
-                    continue;
+                "stack" => {
+                    for word in rest.split_whitespace() {
+                        stack.push(parse_number(Some(word), line_no)?);
+                    }
                 }
 
-                let msg = vec_to_line(&info.script);
-                warn(&msg);
-                info.script.clear();
+                "last" => last = parse_number(Some(rest.trim()), line_no)?,
+
+                _ => return Err(format!("Line {}: unknown entry '{}'.", line_no, kind)),
             }
+        }
 
-            "times" => {
-                if info.stack.is_empty() && info.script.is_empty() {
-                    error("Insufficient arguments!");
-                    break;
-                }
-                let times = info.stack.pop().unwrap();
-                let copy = copy_vec(&info.script);
-                info.script.clear();
-                for _ in 0..times as i32 {
-                    interpret_vec(&copy, info);
+        let mut namespaces = nest(namespaces)?;
+        info.functions = functions;
+        info.docs = docs;
+        info.variables = variables;
+        info.constants = constants;
+
+        // Host functions can't be saved, so they stay with their namespaces.
+        for old in std::mem::take(&mut info.namespaces) {
+            if old.natives.is_empty() {
+                continue;
+            }
+            if info.opened.contains(&old.title) && !opened.contains(&old.title) {
+                opened.push(old.title.to_owned());
+            }
+            match namespaces.iter_mut().find(|namespace| namespace.title == old.title) {
+                Some(namespace) => namespace.natives = old.natives,
+                None => {
+                    let mut namespace = Namespace::new(old.title);
+                    namespace.natives = old.natives;
+                    namespaces.push(namespace);
                 }
             }
+        }
+        info.namespaces = namespaces;
+        info.opened = opened;
+        info.aliases = aliases;
+        info.stack = stack;
+        info.last = last;
+        Ok(())
+    }
 
-            "formats" => {
-                if info.idents.is_empty() || info.stack.is_empty(){
-                    error("Missing arguments required for fomatting.");
-                    break;
-                }
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use crate::interpreter::interpet::interpret_line;
+        use crate::interpreter::test_session as session;
+
+        #[test]
+        fn export_skips_std() {
+            let mut info = session();
+            interpret_line(String::from("-sall { 2 * } double fn"), &mut info);
+            let source = export_source(&info);
+            assert!(source.contains("} double fn"));
+            assert!(!source.contains("std namespace"));
+        }
 
-                let mut target = info.idents.pop().unwrap();
-                error("Not implemented.");
-            }
+        #[test]
+        fn export_opens_namespaces() {
+            let mut info = session();
+            interpret_line(String::from("-sall { { 1 + } inc! fn } counting namespace use counting"), &mut info);
+            let source = export_source(&info);
+            assert!(source.contains("\nuse counting\n"));
+            assert!(!source.contains("use std"));
+
+            let mut info = session();
+            interpret_line(source, &mut info);
+            interpret_line(String::from("5 inc!"), &mut info);
+            assert_eq!(info.error, None);
+            assert_eq!(info.stack, vec![6.0]);
+        }
 
-            "puts" => {
-                if info.idents.is_empty() {
-                    error("Insufficient arguments!");
-                    break;
-                }
+        #[test]
+        fn export_reloads_over_existing_names() {
+            let mut info = session();
+            interpret_line(String::from("-sall x 5 def c 2 const { 1 + } inc fn { y 1 def } ns namespace"), &mut info);
+            let source = export_source(&info);
+            interpret_line(String::from("-sall x 7 def { 2 + } inc fn"), &mut info);
+            info.stack.clear();
+
+            interpret_line(source, &mut info);
+            assert_eq!(info.error, None);
+            assert_eq!(info.variables.get("x"), Some(&5.0));
+            assert_eq!(info.constants.get("c"), Some(&2.0));
+            assert_eq!(info.functions.get("inc"), Some(&vec![String::from("1"), String::from("+")]));
+            assert_eq!(info.namespace("ns").and_then(|ns| ns.variables.get("y")), Some(&1.0));
+            assert!(info.stack.is_empty());
+        }
 
-                let item = info.idents.pop().unwrap();
-                let (res, _)= get_string(&item, 1);
-                print!("{res}");
-                std::io::stdout().flush().expect("");
-            }
+        #[test]
+        fn saved_session_restores() {
+            let path = std::env::temp_dir().join(format!("codebook-session-{}", std::process::id()));
+            let path = path.to_str().unwrap();
+            let mut info = session();
+            interpret_line(String::from("-sall x 5 def c 2 const { 1 + } inc fn { y 1 def } ns namespace use ns"), &mut info);
+            info.stack = vec![1.0, 2.0];
+            save_session(&info, path).unwrap();
+
+            let mut restored = session();
+            restore_session(&mut restored, path).unwrap();
+            assert_eq!(restored.variables, info.variables);
+            assert_eq!(restored.constants, info.constants);
+            assert_eq!(restored.functions, info.functions);
+            assert_eq!(restored.opened, info.opened);
+            assert_eq!(restored.stack, vec![1.0, 2.0]);
+            assert_eq!(restored.namespace("ns").and_then(|ns| ns.variables.get("y")), Some(&1.0));
+        }
 
-            "STACK_SIZE" => {
-                let size = info.stack.len();
-                info.stack.push(size as f32);
-            }
+        #[test]
+        fn restore_rejects_other_files() {
+            let path = std::env::temp_dir().join(format!("codebook-not-a-session-{}", std::process::id()));
+            std::fs::write(&path, "1 2 +").unwrap();
+            let mut info = session();
+            interpret_line(String::from("-sall x 5 def"), &mut info);
+            assert!(restore_session(&mut info, path.to_str().unwrap()).is_err());
+            assert_eq!(info.variables.get("x"), Some(&5.0));
+        }
 
-            "load_file" => {
-                if info.idents.is_empty() {
-                    error("Insufficient arguments!");
-                    break;
-                }
+        #[test]
+        fn export_stops_at_a_different_constant() {
+            let mut info = session();
+            interpret_line(String::from("-sall c 2 const"), &mut info);
+            let source = export_source(&info);
+            let mut other = session();
+            interpret_line(String::from("-sall c 3 const"), &mut other);
+
+            interpret_line(source, &mut other);
+            assert!(other.error.is_some());
+            assert_eq!(other.constants.get("c"), Some(&3.0));
+        }
+    }
 
-                let filepath = info.idents.pop().unwrap();
-                let res = load_file(filepath.as_str(), info).is_ok();
+}
 
-                if !suppress_out && !supress_all && res {
-                    out(&format!("Successfully loaded file '{}'.", filepath));
-                }
-            }
+pub mod natives {
+    use std::rc::Rc;
+    use super::session::SessionInfo;
 
-            "if" => {
-                if info.stack.is_empty() {
-                    error("Insufficient arguments!");
-                    break;
-                }
+    pub type NativeFn = dyn Fn(&[f32]) -> Result<Vec<f32>, String>;
 
-                let res = info.stack.pop().unwrap();
-                if res == 1.0 {
-                    let copy = copy_vec(&info.script);
-                    info.script.clear();
-                    interpret_vec(&copy, info);
-                    info.bool_val = Some(1.0);
-                } else {
-                    info.bool_val = Some(0.0);
-                }
-                info.script.clear();
-            }
+    /// A word implemented in Rust by the host program. It takes `arity` items off the stack,
+    /// oldest first, and pushes whatever it returns.
+    #[derive(Clone)]
+    pub struct NativeFunction {
+        pub arity: usize,
+        pub func: Rc<NativeFn>,
+    }
 
-            "n!" => {
-                if info.stack.is_empty() {
-                    error("Insufficient arguments!");
-                    break;
-                }
+    impl NativeFunction {
+        pub fn new<F>(arity: usize, func: F) -> Self
+        where
+            F: Fn(&[f32]) -> Result<Vec<f32>, String> + 'static,
+        {
+            NativeFunction { arity, func: Rc::new(func) }
+        }
+    }
 
-                let num = info.stack.pop().unwrap();
-                let mut res = 1;
+    /// Pops the arguments of `native`, calls it and pushes its results.
+    pub fn call_native(native: &NativeFunction, info: &mut SessionInfo) -> Result<(), String> {
+        if info.stack.len() < native.arity {
+            return Err(String::from("Insufficient arguments!"));
+        }
 
-                for i in 2..num as i32 + 1 {
-                    res *= i;
-                }
-                info.stack.push(res as f32);
-            }
+        let args = info.stack.split_off(info.stack.len() - native.arity);
+        let results = (native.func)(&args)?;
+        info.stack.extend(results);
+        Ok(())
+    }
 
-            "==" => {
-                if info.stack.len() < 2 {
-                    error("Insufficient arguments!");
-                    break;
-                }
+}
 
-                let rhs = info.stack.pop().unwrap();
-                let lhs = info.stack.pop().unwrap();
-                if rhs == lhs {
-                    info.stack.push(1.0);
-                } else {
-                    info.stack.push(0.0);
-                }
-            },
+pub mod modules {
+    use std::collections::HashMap;
+    use std::env;
+    use std::fs;
+    use std::path::{Path, PathBuf};
+    use super::interpet::{config_dir, interpret_line};
+    use super::namespaces::Namespace;
+    use super::session::SessionInfo;
+
+    /// Which modules have been imported, with the titles of their namespaces, and which are
+    /// being imported right now.
+    #[derive(Default)]
+    pub struct Modules {
+        pub loaded: HashMap<PathBuf, String>,
+        pub loading: Vec<(String, PathBuf)>,
+    }
 
-            "!=" => {
-                if info.stack.len() < 2 {
-                    error("Insufficient arguments!");
-                    break;
-                }
+    /// `$XDG_CONFIG_HOME/codebook/lib` (or `~/.config/codebook/lib`).
+    pub fn user_lib_dir() -> Option<PathBuf> {
+        Some(config_dir()?.join("lib"))
+    }
 
-                let rhs = info.stack.pop().unwrap();
-                let lhs = info.stack.pop().unwrap();
-                if rhs != lhs {
-                    info.stack.push(1.0);
-                } else {
-                    info.stack.push(0.0);
-                }
-            },
+    /// Where `import` looks for modules: the script's directory (or the current directory in the
+    /// REPL), the user lib directory, then each directory in `CODEBOOK_PATH`.
+    pub fn search_path(info: &SessionInfo) -> Vec<PathBuf> {
+        let mut dirs = vec![info.script_dir.clone().unwrap_or_else(|| PathBuf::from("."))];
+        dirs.extend(user_lib_dir());
+        if let Some(path) = env::var_os("CODEBOOK_PATH") {
+            dirs.extend(env::split_paths(&path).filter(|dir| !dir.as_os_str().is_empty()));
+        }
+        dirs
+    }
 
+    pub fn resolve(name: &str, info: &SessionInfo) -> Option<PathBuf> {
+        let file = if name.ends_with(".cb") { name.to_owned() } else { format!("{}.cb", name) };
+        search_path(info).into_iter()
+            .map(|dir| dir.join(&file))
+            .find(|path| path.is_file())
+            .and_then(|path| path.canonicalize().ok())
+    }
 
-            "credits" => out("Codebook created by Amelia Johnson."),
+    pub enum Imported {
+        Loaded,
+        AlreadyLoaded,
+        /// The module raised an error, which has already been reported.
+        Failed,
+    }
 
-            "about" => {
-                out("Not yet implemented.");
-            },
+    /// Imports a module into a namespace named after its file.
+    pub fn import(name: &str, info: &mut SessionInfo) -> Result<Imported, String> {
+        let path = resolve(name, info).ok_or(format!("Module '{}' not found.", name))?;
+        if info.modules.loaded.contains_key(&path) {
+            return Ok(Imported::AlreadyLoaded);
+        }
 
-            "def" => {
-                if info.idents.last().is_none() || info.stack.last().is_none() {
-                    error("Insufficient arguments!");
-                    break;
-                }
+        if info.modules.loading.iter().any(|(_, loading)| *loading == path) {
+            let mut chain: Vec<&str> = info.modules.loading.iter().map(|(name, _)| name.as_str()).collect();
+            chain.push(name);
+            return Err(format!("Import cycle: {}.", chain.join(" -> ")));
+        }
 
-                let key = info.idents.pop().unwrap();
-                let value = info.stack.pop().unwrap();
+        let source = fs::read_to_string(&path).map_err(|_| format!("Could not read module '{}'.", name))?;
+        let title = Path::new(name).file_stem().unwrap_or_default().to_string_lossy().into_owned();
+
+        // The module runs in its own session, which shares the importer's namespaces, open
+        // namespaces, output and module bookkeeping, and resolves its own imports next to itself.
+        let mut module = SessionInfo::new();
+        module.quiet = true;
+        module.script_dir = path.parent().map(Path::to_path_buf);
+        module.natives = info.natives.clone();
+        module.opened = info.opened.clone();
+        let outer: Vec<String> = info.namespaces.iter().map(|namespace| namespace.title.to_owned()).collect();
+        module.namespaces = std::mem::take(&mut info.namespaces);
+        module.modules = std::mem::take(&mut info.modules);
+        std::mem::swap(&mut module.output, &mut info.output);
+        module.modules.loading.push((name.to_owned(), path.clone()));
+
+        interpret_line(source, &mut module);
+
+        module.modules.loading.pop();
+        std::mem::swap(&mut module.output, &mut info.output);
+        info.modules = module.modules;
+        // Namespaces the module defined become children of its namespace, like those defined
+        // inside `namespace`. Modules it imported stay at the top level.
+        let modules: Vec<&String> = info.modules.loaded.values().collect();
+        let (outside, inside): (Vec<Namespace>, Vec<Namespace>) = module.namespaces
+            .into_iter()
+            .partition(|namespace| outer.contains(&namespace.title) || modules.contains(&&namespace.title));
+        info.namespaces = outside;
+        if module.exit_code.is_some() {
+            info.exit_code = module.exit_code;
+        }
+        if let Some(msg) = module.error {
+            info.error.get_or_insert(msg);
+            return Ok(Imported::Failed);
+        }
 
-                if !suppress_out && !supress_all {
-                    out(&format!("{} = {}", key, value));
-                }
+        let mut namespace = Namespace::new(title.to_owned());
+        namespace.functions = module.functions;
+        namespace.docs = module.docs;
+        namespace.variables = module.variables;
+        namespace.constants = module.constants;
+        namespace.uses = module.opened.into_iter().filter(|used| !info.opened.contains(used)).collect();
+        namespace.adopt(inside);
+        info.add_namespace(namespace);
+        info.modules.loaded.insert(path, title);
+        Ok(Imported::Loaded)
+    }
 
-                suppress_out = false;
-                info.variables.insert(key, value);
-            }
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use crate::interpreter::test_session;
+
+        /// A session whose imports are looked up in a fresh directory holding `files`.
+        fn session(test: &str, files: &[(&str, &str)]) -> SessionInfo {
+            let dir = env::temp_dir().join(format!("codebook-{}-{}", test, std::process::id()));
+            fs::create_dir_all(&dir).unwrap();
+            for (name, source) in files {
+                fs::write(dir.join(name), source).unwrap();
+            }
+            let mut info = test_session();
+            info.script_dir = Some(dir);
+            info
+        }
 
-            "const" => {
-                if info.idents.last().is_none() || info.stack.last().is_none() {
-                    error("Insufficient arguments!");
-                    break;
-                }
+        #[test]
+        fn module_namespaces_are_nested() {
+            let mut info = session("nested", &[
+                ("n.cb", "{ { 7 } seven! fn } inner namespace import helper"),
+                ("helper.cb", "h 10 def"),
+            ]);
+            interpret_line(String::from("-sall import n n::inner::seven! helper::h"), &mut info);
+            assert_eq!(info.error, None);
+            assert_eq!(info.stack, vec![7.0, 10.0]);
+            let titles: Vec<&str> = info.namespaces.iter().map(|namespace| namespace.title.as_str()).collect();
+            assert_eq!(titles, vec!["std", "helper", "n"]);
+        }
 
-                let key = info.idents.pop().unwrap();
-                let value = info.stack.pop().unwrap();
+        #[test]
+        fn module_sees_open_namespaces() {
+            let mut info = session("opened", &[("m.cb", "half 2 sqrt! def")]);
+            interpret_line(String::from("-sall m import! m::half"), &mut info);
+            assert_eq!(info.error, None);
+            assert_eq!(info.stack, vec![2f32.sqrt()]);
+            assert!(info.namespace("m").unwrap().uses.is_empty());
+        }
 
-                if info.constants.contains_key(&key) {
-                    error("You cannot define a constant.");
-                    break;
-                }
+        #[test]
+        fn import_cycle_fails() {
+            let mut info = session("cycle", &[("a.cb", "import b"), ("b.cb", "import a")]);
+            interpret_line(String::from("-sall import a"), &mut info);
+            assert_eq!(info.error.as_deref(), Some("Import cycle: a -> b -> a."));
+        }
+    }
 
-                if !suppress_out && !supress_all {
-                    out(&format!("{} = {}", key, value));
-                }
+}
+
+pub mod math {
+    use std::f64::consts::PI;
 
-                suppress_out = false;
+    /// The unit the trigonometric words take and return angles in.
+    #[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+    pub enum AngleMode {
+        Degrees,
+        #[default]
+        Radians,
+        Gradians,
+    }
 
- 
-                info.constants.insert(key, value);
+    impl AngleMode {
+        pub fn name(self) -> &'static str {
+            match self {
+                AngleMode::Degrees => "degrees",
+                AngleMode::Radians => "radians",
+                AngleMode::Gradians => "gradians",
             }
+        }
 
-            "help" => {
-                print_help();
+        pub fn to_radians(self, angle: f64) -> f64 {
+            match self {
+                AngleMode::Degrees => angle.to_radians(),
+                AngleMode::Radians => angle,
+                AngleMode::Gradians => angle * PI / 200.0,
             }
+        }
 
-            "reset" => {
-                if !info.script.is_empty() {
+        pub fn from_radians(self, angle: f64) -> f64 {
+            match self {
+                AngleMode::Degrees => angle.to_degrees(),
+                AngleMode::Radians => angle,
+                AngleMode::Gradians => angle * 200.0 / PI,
+            }
+        }
+    }
 
-                    for ident in info.script.iter() {
-                        if info.variables.contains_key(ident) {
-                            info.variables.remove(ident);
-                            if !suppress_out && !supress_all {
-                                out(&format!("Variable '{}' removed.", ident));
-                            }
-                        } else if info.functions.contains_key(ident) {
-                            info.functions.remove(ident);
-                            if !suppress_out && !supress_all {
-                                out(&format!("Function '{}' removed.", ident));
-                            }
-                        } else {
-                            error(&format!("Could not find identifier '{}'.", ident));
-                        }
-                    }
-                    suppress_out = false;
-                    info.script.clear();
-                    continue;
-                }
+    /// Rounds a result that is zero but for rounding error, so `180 sin` in degrees is 0.
+    pub fn snap(value: f64) -> f32 {
+        if value.abs() < 1e-12 { 0.0 } else { value as f32 }
+    }
 
-                info.reset();
-                if !suppress_out {
-                    out("Variables and functions reset.");
-                }
+    /// The base the REPL shows results in, and the conversion words write numbers in.
+    #[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+    pub enum Base {
+        #[default]
+        Decimal,
+        Hexadecimal,
+        Binary,
+        Octal,
+    }
 
-                suppress_out = false;
+    impl Base {
+        pub fn name(self) -> &'static str {
+            match self {
+                Base::Decimal => "decimal",
+                Base::Hexadecimal => "hexadecimal",
+                Base::Binary => "binary",
+                Base::Octal => "octal",
             }
+        }
 
-            "-s" => {
-                suppress_out = true;
+        /// Writes a whole number with its `0x`, `0b` or `0o` prefix. Fractions, and anything in
+        /// decimal, are written as usual.
+        pub fn format(self, value: f32) -> String {
+            if self == Base::Decimal || value.fract() != 0.0 || value.abs() >= i64::MAX as f32 {
+                return value.to_string();
             }
 
-            "-sall" => {
-                supress_all = true;
+            let sign = if value < 0.0 { "-" } else { "" };
+            let magnitude = (value as i64).unsigned_abs();
+            match self {
+                Base::Hexadecimal => format!("{}0x{:x}", sign, magnitude),
+                Base::Binary => format!("{}0b{:b}", sign, magnitude),
+                Base::Octal => format!("{}0o{:o}", sign, magnitude),
+                Base::Decimal => unreachable!(),
             }
+        }
+    }
 
-            "dup" => {
-                if info.stack.is_empty() {
-                    error("Nothing to duplicate.");
-                    break;
-                }
+    /// Reads a number, either as `f32` does or as a `0x`, `0b` or `0o` integer literal, which
+    /// may start with a `-`.
+    pub fn parse_number(token: &str) -> Option<f32> {
+        let (negative, digits) = match token.strip_prefix('-') {
+            Some(rest) => (true, rest),
+            None => (false, token),
+        };
+        let radix = match digits.get(..2) {
+            Some("0x" | "0X") => 16,
+            Some("0b" | "0B") => 2,
+            Some("0o" | "0O") => 8,
+            _ => return token.parse::<f32>().ok(),
+        };
+
+        let digits = &digits[2..];
+        if digits.is_empty() || !digits.chars().all(|ch| ch.is_digit(radix)) {
+            return None;
+        }
+        let value = i64::from_str_radix(digits, radix).ok()? as f32;
+        Some(if negative { -value } else { value })
+    }
 
-                info.stack.push(*info.stack.last().unwrap());
-            }
+    /// Every whole number up to this size is stored exactly by a stack value.
+    pub const MAX_EXACT: f32 = 16_777_216.0;
 
-            "drop" => {
-                if info.stack.is_empty() {
-                    error("Nothing to drop.");
-                    break;
-                }
+    /// The low `width` bits of `value` in two's complement, padded with zeros to `width` digits.
+    pub fn bits(value: i64, width: u32) -> String {
+        let mask = u64::MAX >> (64 - width);
+        format!("{:0width$b}", value as u64 & mask, width = width as usize)
+    }
+
+    /// The value as an integer, if it is a whole number small enough to be exact.
+    pub fn exact_integer(value: f32) -> Option<i64> {
+        (value.fract() == 0.0 && value.abs() <= MAX_EXACT).then_some(value as i64)
+    }
+
+    pub fn gcd(a: i64, b: i64) -> i64 {
+        let (mut a, mut b) = (a.abs(), b.abs());
+        while b != 0 {
+            (a, b) = (b, a % b);
+        }
+        a
+    }
 
-                info.stack.pop();
+    pub fn lcm(a: i64, b: i64) -> i64 {
+        if a == 0 || b == 0 {
+            return 0;
+        }
+        (a / gcd(a, b) * b).abs()
+    }
+
+    pub fn is_prime(n: i64) -> bool {
+        if n < 2 {
+            return false;
+        }
+        let mut divisor = 2;
+        while divisor * divisor <= n {
+            if n % divisor == 0 {
+                return false;
             }
+            divisor += 1;
+        }
+        true
+    }
 
-            "fn" => {
-                if info.idents.is_empty() {
-                    error("Insufficient arguments!");
-                    break;
-                }
+    /// `base` to the power `exponent`, modulo `modulus`, in the range 0..modulus.
+    pub fn powmod(base: i64, exponent: i64, modulus: i64) -> i64 {
+        let modulus = modulus as i128;
+        let mut base = (base as i128).rem_euclid(modulus);
+        let mut exponent = exponent;
+        let mut result = 1 % modulus;
+        while exponent > 0 {
+            if exponent & 1 == 1 {
+                result = result * base % modulus;
+            }
+            base = base * base % modulus;
+            exponent >>= 1;
+        }
+        result as i64
+    }
 
-                let copy = copy_vec(&info.script);
-                let ident = info.idents.pop().unwrap();
+    pub fn mean(values: &[f32]) -> f64 {
+        values.iter().map(|value| *value as f64).sum::<f64>() / values.len() as f64
+    }
 
-                if !suppress_out && !supress_all {
-                    out(&format!("{} = function {:?}", ident, copy));
-                }
+    /// The middle value of sorted values, or the mean of the middle two.
+    pub fn median(sorted: &[f32]) -> f64 {
+        let middle = sorted.len() / 2;
+        if sorted.len().is_multiple_of(2) {
+            (sorted[middle - 1] as f64 + sorted[middle] as f64) / 2.0
+        } else {
+            sorted[middle] as f64
+        }
+    }
 
-                suppress_out = false;
-                info.functions.insert(ident, copy);
-                info.script.clear();
+    /// The most frequent of sorted values; the smallest one if several are as frequent.
+    pub fn mode(sorted: &[f32]) -> f32 {
+        let (mut best, mut best_count) = (sorted[0], 0);
+        for run in sorted.chunk_by(|a, b| a == b) {
+            if run.len() > best_count {
+                (best, best_count) = (run[0], run.len());
             }
+        }
+        best
+    }
 
-            "clear" => {
-                print!("\x1b[2J");
-                stdout().flush().expect("Could not clear screen.");
-            }
+    /// The population variance, or the sample variance (divided by n - 1) if `sample` is set.
+    pub fn variance(values: &[f32], sample: bool) -> f64 {
+        let mean = mean(values);
+        let squares: f64 = values.iter().map(|value| (*value as f64 - mean).powi(2)).sum();
+        squares / (values.len() - usize::from(sample)) as f64
+    }
 
-            "variables" => {
-                if info.variables.len() == 0 {
-                    out("None");
-                    continue;
-                }
+    /// The `p`th percentile (0 to 100) of sorted values, interpolating between the closest ranks.
+    pub fn percentile(sorted: &[f32], p: f64) -> f64 {
+        let rank = p / 100.0 * (sorted.len() - 1) as f64;
+        let (low, high) = (rank.floor() as usize, rank.ceil() as usize);
+        let fraction = rank - low as f64;
+        sorted[low] as f64 + (sorted[high] as f64 - sorted[low] as f64) * fraction
+    }
 
-                out("\n");
-                for variable in info.variables.keys() {
-                    println!("\t{}{}{}{} = {}", 
-                    BOLD,
-                    variable, 
-                    DEFAULT,
-                    GREEN,
-                    info.variables.get(variable).unwrap());
-                }
-            },
+    /// The slope and intercept of the least-squares line through the points, if the x values
+    /// are not all the same.
+    pub fn linear_regression(points: &[(f32, f32)]) -> Option<(f64, f64)> {
+        let n = points.len() as f64;
+        let mean_x = points.iter().map(|(x, _)| *x as f64).sum::<f64>() / n;
+        let mean_y = points.iter().map(|(_, y)| *y as f64).sum::<f64>() / n;
+        let mut covariance = 0.0;
+        let mut spread = 0.0;
+        for (x, y) in points {
+            covariance += (*x as f64 - mean_x) * (*y as f64 - mean_y);
+            spread += (*x as f64 - mean_x).powi(2);
+        }
+        if spread == 0.0 {
+            return None;
+        }
+        let slope = covariance / spread;
+        Some((slope, mean_y - slope * mean_x))
+    }
 
-            "ansi" => {
+    /// The prime factors of `n`, smallest first, repeated as often as they divide it.
+    pub fn factorize(n: i64) -> Vec<i64> {
+        let mut factors = Vec::new();
+        let mut n = n;
+        let mut divisor = 2;
+        while divisor * divisor <= n {
+            while n % divisor == 0 {
+                factors.push(divisor);
+                n /= divisor;
+            }
+            divisor += 1;
+        }
+        if n > 1 {
+            factors.push(n);
+        }
+        factors
+    }
 
-                if info.script.is_empty() {
-                    error("Insufficient arguments!");
-                    break;
-                }
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn bits_are_padded_twos_complement() {
+            assert_eq!(bits(10, 8), "00001010");
+            assert_eq!(bits(-1, 4), "1111");
+            assert_eq!(bits(-2, 32), format!("{}0", "1".repeat(31)));
+            assert_eq!(bits(5, 64).len(), 64);
+            assert_eq!(bits(256, 8), "00000000");
+        }
 
-                let cmd = vec_to_line(&info.script);
-                print!("\x1b{}", cmd);
-                stdout().flush().expect("Could not execute ANSI command.");
-                info.script.clear();
-            },
+        #[test]
+        fn angle_modes_convert_through_radians() {
+            assert_eq!(AngleMode::Degrees.to_radians(180.0), PI);
+            assert_eq!(AngleMode::Gradians.to_radians(200.0), PI);
+            assert_eq!(AngleMode::Radians.to_radians(1.5), 1.5);
+            assert!((AngleMode::Degrees.from_radians(PI / 2.0) - 90.0).abs() < 1e-9);
+            assert!((AngleMode::Gradians.from_radians(PI) - 200.0).abs() < 1e-9);
+            assert_eq!(snap(PI.sin()), 0.0);
+        }
 
-            "namespaces" => {
-                if info.namespaces.is_empty() {
-                    out("None");
-                    continue;
-                }
+        #[test]
+        fn number_theory() {
+            assert_eq!(gcd(-12, 18), 6);
+            assert_eq!(lcm(4, 6), 12);
+            assert_eq!(lcm(0, 6), 0);
+            assert!(is_prime(97));
+            assert!(!is_prime(1));
+            assert!(!is_prime(91));
+            assert_eq!(powmod(4, 13, 497), 445);
+            assert_eq!(powmod(-2, 3, 5), 2);
+            assert_eq!(factorize(360), vec![2, 2, 2, 3, 3, 5]);
+            assert_eq!(exact_integer(2.0f32.powi(24)), Some(1 << 24));
+            assert_eq!(exact_integer(1e30), None);
+        }
 
-                out("\n");
+        #[test]
+        fn statistics() {
+            let sorted = [1.0, 2.0, 2.0, 3.0, 7.0];
+            assert_eq!(mean(&sorted), 3.0);
+            assert_eq!(median(&sorted), 2.0);
+            assert_eq!(median(&[1.0, 2.0, 3.0, 4.0]), 2.5);
+            assert_eq!(mode(&sorted), 2.0);
+            assert_eq!(mode(&[1.0, 2.0]), 1.0);
+            assert_eq!(variance(&sorted, false), 4.4);
+            assert_eq!(variance(&sorted, true), 5.5);
+            assert_eq!(percentile(&sorted, 50.0), 2.0);
+            assert_eq!(percentile(&[10.0, 20.0], 25.0), 12.5);
+            assert_eq!(linear_regression(&[(1.0, 3.0), (2.0, 5.0), (3.0, 7.0)]), Some((2.0, 1.0)));
+            assert_eq!(linear_regression(&[(1.0, 3.0), (1.0, 5.0)]), None);
+        }
 
-                for namespace in &info.namespaces {
-                    println!("\t{BOLD}{}{DEFAULT}{GREEN}", namespace.title);
-                }
+        #[test]
+        fn base_literals_round_trip() {
+            assert_eq!(parse_number("0xff"), Some(255.0));
+            assert_eq!(parse_number("-0b101"), Some(-5.0));
+            assert_eq!(parse_number("0o17"), Some(15.0));
+            assert_eq!(parse_number("0xg"), None);
+            assert_eq!(parse_number("2.5"), Some(2.5));
+            assert_eq!(Base::Hexadecimal.format(255.0), "0xff");
+            assert_eq!(Base::Binary.format(-5.0), "-0b101");
+            assert_eq!(Base::Octal.format(1.5), "1.5");
+        }
+    }
+
+}
+
+pub mod history {
+    use std::collections::{HashMap, VecDeque};
+
+    pub const HISTORY_LIMIT: usize = 100;
+
+    #[derive(Clone, PartialEq)]
+    pub struct Snapshot {
+        pub stack: Vec<f32>,
+        pub variables: HashMap<String, f32>,
+        pub functions: HashMap<String, Vec<String>>,
+        pub last: f32,
+    }
+
+    pub struct History {
+        pub pending: Option<Snapshot>,
+        undo: VecDeque<Snapshot>,
+        redo: Vec<Snapshot>,
+    }
+
+    impl History {
+        pub fn new() -> Self {
+            Self {
+                pending: None,
+                undo: VecDeque::new(),
+                redo: Vec::new(),
             }
+        }
 
-            "functions" => {
-                if info.functions.len() == 0 && info.namespaces.is_empty(){
-                    out("None");
-                    continue;
-                }
+        pub fn checkpoint(&mut self, snapshot: Snapshot) {
+            self.pending = Some(snapshot);
+        }
 
-                out("\n");
-
-                for namespace in &info.namespaces {
-                    println!("\tNAMESPACE: {UNDERLINE}{BOLD}{}{DEFAULT}{GREEN}", namespace.title);
-                    for func in namespace.functions.keys() {
-                        println!(
-                            "\t{}{}{}{} = {:?}",
-                            BOLD,
-                            func,
-                            DEFAULT,
-                            GREEN,
-                            namespace.functions.get(func).unwrap()
-                        );
-                    }
+        pub fn commit(&mut self, current: Snapshot) {
+            if let Some(before) = self.pending.take() {
+                if before == current {
+                    return;
                 }
 
-                println!("\t{UNDERLINE}{BOLD}N/A{DEFAULT}{GREEN}");
-                for function in info.functions.keys() {
-                    println!(
-                        "\t{}{}{}{} = {:?}",
-                        BOLD,
-                        function,
-                        DEFAULT,
-                        GREEN,
-                        info.functions.get(function).unwrap()
-                    );
+                self.undo.push_back(before);
+                if self.undo.len() > HISTORY_LIMIT {
+                    self.undo.pop_front();
                 }
+                self.redo.clear();
             }
+        }
 
-            "out" => {
-                if info.script.is_empty() {
-                    out("");
-                    continue;
-                }
+        pub fn undo(&mut self, current: Snapshot) -> Option<Snapshot> {
+            let previous = self.undo.pop_back()?;
+            self.redo.push(current);
+            Some(previous)
+        }
 
-                let res = vec_to_line(&info.script);
-                out(&res);
-                info.script.clear();
-            }
+        pub fn redo(&mut self, current: Snapshot) -> Option<Snapshot> {
+            let next = self.redo.pop()?;
+            self.undo.push_back(current);
+            Some(next)
+        }
+    }
 
-            "flush" => {
-                if info.script.is_empty() {
-                    println!("");
-                    continue;
-                }
+    impl Default for History {
+        fn default() -> Self {
+            Self::new()
+        }
+    }
 
-                let res = vec_to_line(&info.script);
-                println!("{}", res);
-                info.script.clear();
-            }
+    #[cfg(test)]
+    mod tests {
+        use super::*;
 
-            "namespace" => {
-                if info.idents.is_empty() {
-                    error("Namespace requires a name.");
-                    break;
-                }
+        fn snapshot(top: f32) -> Snapshot {
+            Snapshot { stack: vec![top], variables: HashMap::new(), functions: HashMap::new(), last: top }
+        }
 
-                let title = info.idents.pop().unwrap();
-                let mut namespace = Namespace::new(title.to_owned());
-                let mut namespace_session = SessionInfo::new();
-                interpret_line(vec_to_line(&info.script), &mut namespace_session);
-                for func in namespace_session.functions {
-                    let name = func.0;
-                    let contents = func.1;
-                    namespace.functions.insert(name, contents);
-                }
-                info.namespaces.push(namespace);
-                info.script.clear();
-                
-            },
+        fn entry(history: &mut History, before: f32, after: f32) {
+            history.checkpoint(snapshot(before));
+            history.commit(snapshot(after));
+        }
 
-            "pushch" => {
-                if info.stack.is_empty() {
-                    error("Insufficient arguments!");
-                    break;
-                }
+        #[test]
+        fn undo_and_redo() {
+            let mut history = History::new();
+            entry(&mut history, 0.0, 1.0);
+            entry(&mut history, 1.0, 2.0);
+
+            assert_eq!(history.undo(snapshot(2.0)).map(|s| s.last), Some(1.0));
+            assert_eq!(history.undo(snapshot(1.0)).map(|s| s.last), Some(0.0));
+            assert!(history.undo(snapshot(0.0)).is_none());
+            assert_eq!(history.redo(snapshot(0.0)).map(|s| s.last), Some(1.0));
+            assert_eq!(history.redo(snapshot(1.0)).map(|s| s.last), Some(2.0));
+            assert!(history.redo(snapshot(2.0)).is_none());
+        }
 
-                if info.script.is_empty() {
-                    let item = String::from(info.stack.pop().unwrap().to_string());
-                    info.script.push(item);
-                } else {
-                    let item = String::from(info.stack.pop().unwrap().to_string());
-                    let mut last = info.script.pop().unwrap();
-                    last.push_str(item.as_str());
-                    info.script.push(last);
-                }
-            },
-
-            _ => {
-                if let Ok(num) = token.parse::<f32>() {
-                    info.stack.push(num);
-                } else if info.contains_function(token.to_string()) {
-                    let func = info.get_function(token.to_string());
-                    //println!("{func:?}");
-                    interpret_vec(&func, info);
-                } else {
-                    if token.trim() == "{" || token.trim() == "}" {
-                        continue;
-                    }
-                    info.idents.push(token.to_owned());
-                }
+        #[test]
+        fn unchanged_entries_are_not_recorded() {
+            let mut history = History::new();
+            entry(&mut history, 1.0, 1.0);
+            assert!(history.undo(snapshot(1.0)).is_none());
+        }
+
+        #[test]
+        fn new_entry_clears_redo() {
+            let mut history = History::new();
+            entry(&mut history, 0.0, 1.0);
+            history.undo(snapshot(1.0));
+            entry(&mut history, 0.0, 5.0);
+            assert!(history.redo(snapshot(5.0)).is_none());
+        }
+
+        #[test]
+        fn undo_is_limited() {
+            let mut history = History::new();
+            for step in 0..HISTORY_LIMIT + 10 {
+                entry(&mut history, step as f32, step as f32 + 1.0);
+            }
+            let mut undone = 0;
+            let mut oldest = (HISTORY_LIMIT + 10) as f32;
+            while let Some(previous) = history.undo(snapshot(oldest)) {
+                oldest = previous.last;
+                undone += 1;
             }
+            assert_eq!(undone, HISTORY_LIMIT);
+            assert_eq!(oldest, 10.0);
         }
     }
-}
 
 }
 
 pub mod namespaces {
     use std::collections::HashMap;
+    use super::builtins::check_builtin;
+    use super::natives::NativeFunction;
+    use super::session::Word;
 
     pub struct Namespace {
         pub title: String,
-        pub functions: HashMap<String, Vec<String>>
+        pub functions: HashMap<String, Vec<String>>,
+        pub docs: HashMap<String, String>,
+        pub natives: HashMap<String, NativeFunction>,
+        pub variables: HashMap<String, f32>,
+        pub constants: HashMap<String, f32>,
+        /// Namespaces defined inside this one, reached as `title::child`.
+        pub children: Vec<Namespace>,
+        /// Namespaces opened while this one was defined, visible to its functions.
+        pub uses: Vec<String>,
     }
 
     impl Namespace {
         pub fn new(title: String) -> Self {
             Self {
-                title: title,
+                title,
                 functions: HashMap::new(),
+                docs: HashMap::new(),
+                natives: HashMap::new(),
+                variables: HashMap::new(),
+                constants: HashMap::new(),
+                children: Vec::new(),
+                uses: Vec::new(),
+            }
+        }
+
+        pub fn word(&self, name: &str) -> Option<Word> {
+            if let Some(body) = self.functions.get(name) {
+                return Some(Word::Function(body.to_vec(), Some(self.title.to_owned())));
+            }
+            if let Some(native) = self.natives.get(name) {
+                return Some(Word::Native(native.clone()));
+            }
+            self.variables.get(name)
+                .or_else(|| self.constants.get(name))
+                .map(|value| Word::Value(*value))
+        }
+
+        pub fn child(&self, title: &str) -> Option<&Namespace> {
+            self.children.iter().find(|child| child.title == title)
+        }
+
+        /// Makes `children` child namespaces of this one. Names opened with `use` that
+        /// referred to them, here or inside them, are qualified with this namespace's title.
+        pub fn adopt(&mut self, children: Vec<Namespace>) {
+            let titles: Vec<String> = children.iter().map(|child| child.title.to_owned()).collect();
+            self.children.extend(children);
+            let prefix = self.title.to_owned();
+            self.requalify(&titles, &prefix);
+        }
+
+        fn requalify(&mut self, titles: &[String], prefix: &str) {
+            for used in &mut self.uses {
+                let head = used.split("::").next().unwrap_or_default();
+                if titles.iter().any(|title| title == head) {
+                    *used = format!("{}::{}", prefix, used);
+                }
+            }
+            for child in &mut self.children {
+                child.requalify(titles, prefix);
+            }
+        }
+
+        /// Whether the namespace holds anything written in CSL, as opposed to only host functions.
+        pub fn has_source(&self) -> bool {
+            !self.functions.is_empty() || !self.variables.is_empty() || !self.constants.is_empty()
+                || self.children.iter().any(Namespace::has_source)
+        }
+
+        /// Adds a host function to the namespace; see [`NativeFunction`]. Fails if `name` is a
+        /// built-in word or constant.
+        pub fn register<F>(&mut self, name: &str, arity: usize, func: F) -> Result<(), String>
+        where
+            F: Fn(&[f32]) -> Result<Vec<f32>, String> + 'static,
+        {
+            check_builtin(name)?;
+            self.natives.insert(name.to_owned(), NativeFunction::new(arity, func));
+            Ok(())
+        }
+    }
+
+    /// Every namespace in `namespaces`, children included, with its full `parent::child` path.
+    pub fn all_namespaces(namespaces: &[Namespace]) -> Vec<(String, &Namespace)> {
+        fn walk<'a>(path: String, namespace: &'a Namespace, found: &mut Vec<(String, &'a Namespace)>) {
+            found.push((path.to_owned(), namespace));
+            for child in &namespace.children {
+                walk(format!("{}::{}", path, child.title), child, found);
             }
         }
+
+        let mut found = Vec::new();
+        for namespace in namespaces {
+            walk(namespace.title.to_owned(), namespace, &mut found);
+        }
+        found
     }
 
-}
\ No newline at end of file
+}
+
+/// A session with std loaded that discards its output, for tests.
+#[cfg(test)]
+pub(crate) fn test_session() -> session::SessionInfo {
+    let mut info = session::SessionInfo::new();
+    info.output = output::Output::new(Box::new(output::NullSink), false);
+    interpet::load_stdlib(&mut info);
+    info
+}
@@ -0,0 +1,206 @@
+//! Codebook 2 as a library: the Codebook Scripting Language (CSL) interpreter behind the
+//! `codebook2` calculator, for embedding in other tools.
+//!
+//! ```no_run
+//! use codebook2::Interpreter;
+//!
+//! let mut csl = Interpreter::with_stdlib();
+//! csl.set_var("price", 4.5).unwrap();
+//! let stack = csl.eval("price 3 *").unwrap();
+//! assert_eq!(stack, vec![13.5]);
+//! ```
+
+use std::fmt;
+use std::path::Path;
+
+pub mod interpreter;
+
+use interpreter::interpet;
+use interpreter::namespaces::Namespace;
+use interpreter::natives::NativeFunction;
+use interpreter::output::Sink;
+use interpreter::session::{SessionInfo, Word};
+
+/// A value on the CSL stack.
+pub type Value = f32;
+
+/// An error raised while evaluating CSL source.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CslError {
+    pub message: String,
+}
+
+impl fmt::Display for CslError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl std::error::Error for CslError {}
+
+/// A CSL interpreter. The stack, variables, functions and namespaces persist between calls
+/// to [`Interpreter::eval`].
+pub struct Interpreter {
+    session: SessionInfo,
+}
+
+impl Interpreter {
+    /// An interpreter with an empty session. Optional output (`oout`, `def` messages, ...)
+    /// is suppressed; see [`Interpreter::set_quiet`].
+    pub fn new() -> Self {
+        let mut session = SessionInfo::new();
+        session.quiet = true;
+        Interpreter { session }
+    }
+
+    /// An interpreter with the bundled `std` namespace loaded.
+    pub fn with_stdlib() -> Self {
+        let mut interpreter = Self::new();
+        interpreter.load_stdlib();
+        interpreter
+    }
+
+    pub fn load_stdlib(&mut self) {
+        interpet::load_stdlib(&mut self.session);
+    }
+
+    /// Runs a CSL file in this interpreter, as `load_file` does.
+    pub fn load_file(&mut self, path: &str) -> Result<(), CslError> {
+        self.session.error = None;
+        let _ = interpet::load_file(path, &mut self.session);
+        self.finish().map(|_| ())
+    }
+
+    /// Evaluates `source` and returns the stack afterwards, or the first error it raised.
+    pub fn eval(&mut self, source: &str) -> Result<Vec<Value>, CslError> {
+        self.session.error = None;
+        self.session.exit_code = None;
+        interpet::interpret_line(source.to_owned(), &mut self.session);
+        self.finish()
+    }
+
+    fn finish(&mut self) -> Result<Vec<Value>, CslError> {
+        self.session.idents.clear();
+        match self.session.error.take() {
+            Some(message) => Err(CslError { message }),
+            None => Ok(self.session.stack.clone()),
+        }
+    }
+
+    /// The status code passed to `exit` during the last evaluation, if any.
+    pub fn exit_code(&self) -> Option<i32> {
+        self.session.exit_code
+    }
+
+    pub fn stack(&self) -> &[Value] {
+        &self.session.stack
+    }
+
+    pub fn push(&mut self, value: Value) {
+        self.session.stack.push(value);
+    }
+
+    pub fn pop(&mut self) -> Option<Value> {
+        self.session.stack.pop()
+    }
+
+    pub fn clear_stack(&mut self) {
+        self.session.stack.clear();
+    }
+
+    /// Looks up a variable, or a constant (including built-in ones such as `pi`) if no variable
+    /// has that name. Values of namespaces are found as `ns::name`, or unqualified if the
+    /// namespace is open.
+    pub fn get_var(&self, name: &str) -> Option<Value> {
+        if let Some(value) = self.session.variables.get(name).copied().or_else(|| self.session.constant(name)) {
+            return Some(value);
+        }
+        match self.session.lookup_word(name) {
+            Ok(Some(Word::Value(value))) => Some(value),
+            _ => None,
+        }
+    }
+
+    /// Sets a variable. Fails if `name` is a constant.
+    pub fn set_var(&mut self, name: &str, value: Value) -> Result<(), CslError> {
+        self.session.check_binding(name).map_err(|message| CslError { message })?;
+        self.session.variables.insert(name.to_owned(), value);
+        Ok(())
+    }
+
+    /// Makes a Rust function callable from CSL as `name`. It receives the top `arity` items of
+    /// the stack, oldest first, and its results are pushed back; an `Err` is raised as a CSL error.
+    /// Fails if `name` is a built-in word or a constant.
+    ///
+    /// ```
+    /// let mut csl = codebook2::Interpreter::new();
+    /// csl.register("price", 1, |args| Ok(vec![args[0] * 2.5])).unwrap();
+    /// assert_eq!(csl.eval("4 price").unwrap(), vec![10.0]);
+    /// assert!(csl.register("sqrt", 1, |args| Ok(vec![args[0]])).is_err());
+    /// ```
+    pub fn register<F>(&mut self, name: &str, arity: usize, func: F) -> Result<(), CslError>
+    where
+        F: Fn(&[Value]) -> Result<Vec<Value>, String> + 'static,
+    {
+        self.session.check_binding(name).map_err(|message| CslError { message })?;
+        self.session.natives.insert(name.to_owned(), NativeFunction::new(arity, func));
+        Ok(())
+    }
+
+    /// Adds a namespace, such as one holding functions added with [`Namespace::register`],
+    /// and opens it so its functions can be called unqualified.
+    pub fn add_namespace(&mut self, namespace: Namespace) {
+        let title = namespace.title.to_owned();
+        self.session.add_namespace(namespace);
+        self.session.open_namespace(&title);
+    }
+
+    /// Sets the arguments seen by `ARGC` and `arg`.
+    pub fn set_args(&mut self, args: Vec<String>) {
+        self.session.args = args;
+    }
+
+    /// Sends all output to `sink` instead of standard output.
+    ///
+    /// ```
+    /// use codebook2::interpreter::output::BufferSink;
+    ///
+    /// let mut csl = codebook2::Interpreter::new();
+    /// let buffer = BufferSink::new();
+    /// csl.set_output(Box::new(buffer.clone()));
+    /// csl.eval("{ hello } out").unwrap();
+    /// assert_eq!(buffer.take(), "[Out] >> hello\n");
+    /// ```
+    pub fn set_output(&mut self, sink: Box<dyn Sink>) {
+        self.session.output.sink = sink;
+    }
+
+    /// Turns ANSI colors in the output on or off. They are off by default.
+    pub fn set_color(&mut self, color: bool) {
+        self.session.output.color = color;
+    }
+
+    /// Sets the directory `import` searches first, normally the running script's directory.
+    pub fn set_script_dir(&mut self, dir: &Path) {
+        self.session.script_dir = Some(dir.to_path_buf());
+    }
+
+    /// Shows or hides optional output.
+    pub fn set_quiet(&mut self, quiet: bool) {
+        self.session.quiet = quiet;
+    }
+
+    pub fn session(&self) -> &SessionInfo {
+        &self.session
+    }
+
+    pub fn session_mut(&mut self) -> &mut SessionInfo {
+        &mut self.session
+    }
+}
+
+impl Default for Interpreter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
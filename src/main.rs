@@ -1,78 +1,227 @@
 use std::io;
 use std::io::Read;
-use std::io::stdout;
-use std::io::Write;
-
-pub mod interpreter;
-use interpreter::interpet::interpret_line;
-use interpreter::session::SessionInfo;
-use interpreter::interpet;
-
-fn arguments() {
-    let args: Vec<String> = std::env::args().collect();
-    let mut index = 1;
-    while index < args.len() {
-        let arg = &args[index];
-        match arg.as_str() {
-
-            "f" => {
-                index += 1;
-                if let Ok(mut file) = std::fs::File::open(&args[index]) {
-                    let mut buffer = String::new();
-                    file.read_to_string(&mut buffer).expect("");
-                    let mut session = SessionInfo::new();
-                    interpret_line(buffer, &mut session);
-                    std::process::exit(0);
-                } else {
-                    panic!("File not found!");
-                }
-            }
+use std::process::exit;
 
+mod cli;
+use cli::{Command, Options};
+use codebook2::interpreter::{self, interpet};
+use codebook2::interpreter::output::{Output, StderrSink};
+use codebook2::Interpreter;
+
+/// Loads std and the startup file. Callers set `quiet` first, since it also covers the startup file's output.
+fn startup(csl: &mut Interpreter, options: &Options) {
+    if options.stdlib {
+        csl.load_stdlib();
+    }
 
-            _ => panic!("Unrecognized argument '{arg}'"),
+    if options.no_config {
+        return;
+    }
+
+    if let Some(path) = &options.config {
+        let _ = csl.load_file(path);
+    } else if let Some(path) = interpet::default_config_path() {
+        if path.exists() {
+            let _ = csl.load_file(&path.to_string_lossy());
         }
     }
 }
 
+/// Leaves the process if the last evaluation ran `exit`.
+fn exit_if_requested(csl: &mut Interpreter) {
+    if let Some(code) = csl.exit_code() {
+        csl.session_mut().output.set_color(interpet::DEFAULT);
+        exit(code);
+    }
+}
+
+fn read_source(path: &str, options: &Options) -> String {
+    let mut buffer = String::new();
+    let mut output = Output::stdout(options.color.enabled());
+    output.errors = Some(Box::new(StderrSink));
+    match std::fs::File::open(path) {
+        Ok(mut file) => {
+            if file.read_to_string(&mut buffer).is_err() {
+                output.error(&format!("Could not read file '{}'.", path));
+                exit(1);
+            }
+            buffer
+        }
+        Err(_) => {
+            output.error(&format!("File '{}' not found.", path));
+            exit(1);
+        }
+    }
+}
+
+fn run_script(path: &str, options: &Options) -> ! {
+    let buffer = read_source(path, options);
+    let mut csl = Interpreter::new();
+    csl.set_color(options.color.enabled());
+    csl.set_args(options.script_args.clone());
+    if let Some(dir) = std::path::Path::new(path).parent() {
+        csl.set_script_dir(dir);
+    }
+    csl.set_quiet(options.quiet);
+    startup(&mut csl, options);
+    let result = csl.eval(&buffer);
+    exit_if_requested(&mut csl);
+    csl.session_mut().output.set_color(interpet::DEFAULT);
+    match result {
+        Ok(_) => exit(0),
+        Err(_) => exit(1),
+    }
+}
+
+/// Evaluates a one-shot expression and prints the top of the stack undecorated,
+/// so the result can be consumed by other programs. Errors go to stderr instead.
+fn eval(expr: &str, options: &Options) -> ! {
+    let mut csl = Interpreter::new();
+    csl.session_mut().output.errors = Some(Box::new(StderrSink));
+    csl.set_quiet(true);
+    startup(&mut csl, options);
+    let result = csl.eval(expr);
+    exit_if_requested(&mut csl);
+    if result.is_err() {
+        exit(1);
+    }
+    if let Some(res) = csl.pop() {
+        println!("{}", res);
+    }
+    exit(0);
+}
+
+fn eval_stdin(options: &Options) -> ! {
+    let mut buffer = String::new();
+    if io::stdin().read_to_string(&mut buffer).is_err() {
+        eprintln!("codebook2: could not read standard input.");
+        exit(1);
+    }
+    eval(&buffer, options);
+}
+
+fn check(path: &str, options: &Options) -> ! {
+    let source = read_source(path, options);
+    let mut output = Output::stdout(options.color.enabled());
+    output.errors = Some(Box::new(StderrSink));
+    let problems = interpet::check_source(&source);
+    for problem in &problems {
+        output.error(&format!("{}: {}", path, problem));
+    }
+    if problems.is_empty() && !options.quiet {
+        output.out(&format!("No problems found in '{}'.", path));
+    }
+    output.set_color(interpet::DEFAULT);
+    if problems.is_empty() {
+        exit(0);
+    }
+    exit(1);
+}
+
 fn main() {
-    arguments();
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let options = match cli::parse(&args) {
+        Ok(options) => options,
+        Err(msg) => {
+            eprintln!("codebook2: {}", msg);
+            eprintln!("Try 'codebook2 --help' for more information.");
+            exit(2);
+        }
+    };
+
+    match &options.command {
+        Command::Repl => (),
+        Command::Run(path) => run_script(path, &options),
+        Command::Eval(expr) => eval(expr, &options),
+        Command::Stdin => eval_stdin(&options),
+        Command::Check(path) => check(path, &options),
+        Command::Version => {
+            println!("codebook2 {}", env!("CARGO_PKG_VERSION"));
+            return;
+        }
+        Command::Help => {
+            println!("{}", cli::USAGE);
+            return;
+        }
+    }
+
     use interpet::*;
-    println!();
-    println!("Welcome to Codebook 2.0! The programmable CLI calculator.");
-    println!("Type '{}{}help{}' for help, and '{}{}quit{}' to terminate.", 
-                GREEN, BOLD, DEFAULT, RED, BOLD, DEFAULT);
-    let mut session = SessionInfo::new();
-    let _ = load_file("./src/config.cb", &mut session);
+    let mut csl = Interpreter::new();
+    csl.set_color(options.color.enabled());
+    if !options.quiet {
+        let output = &mut csl.session_mut().output;
+        output.println("");
+        output.println("Welcome to Codebook 2.0! The programmable CLI calculator.");
+        output.println(&format!("Type '{}{}help{}' for help, and '{}{}quit{}' to terminate.",
+                    output.paint(GREEN), output.paint(BOLD), output.paint(DEFAULT),
+                    output.paint(RED), output.paint(BOLD), output.paint(DEFAULT)));
+    }
+    csl.set_quiet(options.quiet);
+    startup(&mut csl, &options);
+    exit_if_requested(&mut csl);
+    let session = csl.session_mut();
+    if let Some(path) = &options.session {
+        match interpreter::storage::restore_session(session, path) {
+            Ok(()) => session.output.out(&format!("Session restored from '{}'.", path)),
+            Err(msg) => session.output.error(&msg),
+        }
+    }
+    session.persistent = session.persistent || options.persistent;
     loop {
-        let line = read_line();
+        let session = csl.session_mut();
+        let line = read_line(&mut session.output);
 
         if line.trim() == "quit" || line.is_empty() {
             let mut confirmation: String = String::new();
-                set_color(YELLOW);
-                print!("Are you sure you'd like to quit? [Y/n]: ");
-                set_color(GREEN);
-                stdout().flush().expect("Couldn't flush console.");
-                io::stdin()
+                session.output.set_color(YELLOW);
+                session.output.print("Are you sure you'd like to quit? [Y/n]: ");
+                session.output.set_color(GREEN);
+                session.output.flush();
+                let read = io::stdin()
                     .read_line(&mut confirmation)
                     .expect("Could not read input.");
 
-                
 
-                if confirmation.trim() == "Y" {
-                    set_color(DEFAULT);
+
+                if confirmation.trim() == "Y" || read == 0 {
+                    session.output.set_color(DEFAULT);
                     break;
                 }
         }
 
-        interpret_line(line, &mut session);
-        if session.stack.last().is_some() {
-            let res = session.stack.pop().unwrap();
-            out(&format!("{}", res));
-            session.last = res;
+        session.checkpoint();
+        let pending_doc = session.pending_doc.clone();
+        interpret_line(line, session);
+        if session.exit_code.is_some() {
+            break;
+        }
+        if session.pending_doc == pending_doc {
+            session.pending_doc = None;
+        }
+        if session.panel {
+            print_panel(session);
+        }
+
+        if session.persistent {
+            if let Some(top) = session.stack.last() {
+                session.last = *top;
+                if !session.panel {
+                    session.output.out(&stack_line(&session.stack, session.base));
+                }
+            }
+        } else {
+            if session.stack.last().is_some() {
+                let res = session.stack.pop().unwrap();
+                session.output.out(&session.base.format(res));
+                session.last = res;
+            }
+
+            session.stack.clear();
         }
 
-        session.stack.clear();
         session.idents.clear();
-        println!();
+        session.commit();
+        session.output.println("");
     }
+    exit_if_requested(&mut csl);
 }
@@ -0,0 +1,132 @@
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+
+fn codebook(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_codebook2"))
+        .arg("--no-config")
+        .args(args)
+        .env("NO_COLOR", "1")
+        .output()
+        .expect("Could not run codebook2.")
+}
+
+fn script(name: &str, source: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("codebook-cli-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join(name);
+    std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+    std::fs::write(&path, source).unwrap();
+    path
+}
+
+fn run(path: &Path) -> Output {
+    codebook(&["run", path.to_str().unwrap()])
+}
+
+#[test]
+fn script_exit_codes() {
+    assert_eq!(run(&script("ok.cb", "1 1 +")).status.code(), Some(0));
+    assert_eq!(run(&script("failing.cb", "foo +")).status.code(), Some(1));
+    assert_eq!(codebook(&["run", "missing.cb"]).status.code(), Some(1));
+}
+
+#[test]
+fn invalid_arguments_exit_with_status_2() {
+    assert_eq!(codebook(&["--bogus"]).status.code(), Some(2));
+    assert_eq!(codebook(&["run"]).status.code(), Some(2));
+}
+
+fn stdout(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stdout).into_owned()
+}
+
+#[test]
+fn eval_prints_the_result() {
+    let output = codebook(&["-e", "5 5 +"]);
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(stdout(&output), "10\n");
+}
+
+#[test]
+fn eval_error_goes_to_stderr() {
+    for expr in ["1 +", "5 3 0 powmod"] {
+        let output = codebook(&["-e", expr]);
+        assert_eq!(output.status.code(), Some(1), "{}", expr);
+        assert_eq!(stdout(&output), "", "{}", expr);
+        assert!(String::from_utf8_lossy(&output.stderr).contains("[Error]:"), "{}", expr);
+    }
+}
+
+#[test]
+fn exit_sets_the_status() {
+    assert_eq!(run(&script("exit.cb", "3 exit")).status.code(), Some(3));
+}
+
+#[test]
+fn arg_pushes_strings_as_written() {
+    let path = script("arg.cb", "0 arg puts");
+    let output = codebook(&["run", path.to_str().unwrap(), "a \"b\" \\n c"]);
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(stdout(&output), "a \"b\" \\n c");
+}
+
+#[test]
+fn non_ascii_source() {
+    let output = codebook(&["-e", "\"é\" puts"]);
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(stdout(&output), "é");
+
+    let output = run(&script("comment.cb", "5 # π is pi\n1 + print"));
+    assert_eq!(output.status.code(), Some(0));
+}
+
+#[test]
+fn import_cycle_fails() {
+    script("cycle_b.cb", "import cycle_a");
+    let output = run(&script("cycle_a.cb", "import cycle_b"));
+    assert_eq!(output.status.code(), Some(1));
+    assert!(stdout(&output).contains("Import cycle: cycle_b -> cycle_a -> cycle_b."));
+}
+
+#[test]
+fn colors_only_on_request_off_a_terminal() {
+    let path = script("colors.cb", "{ hi } out");
+    let path = path.to_str().unwrap();
+    let plain = Command::new(env!("CARGO_BIN_EXE_codebook2"))
+        .args(["--no-config", "run", path])
+        .env_remove("NO_COLOR")
+        .output()
+        .unwrap();
+    assert!(!stdout(&plain).contains('\x1b'));
+    assert!(stdout(&codebook(&["--color=always", "run", path])).contains('\x1b'));
+}
+
+#[test]
+fn eval_keeps_the_config_quiet() {
+    let config = script("config/codebook/config.cb", "x 5 def");
+    let home = config.parent().unwrap().parent().unwrap();
+    let output = Command::new(env!("CARGO_BIN_EXE_codebook2"))
+        .args(["-e", "1 2 + x +"])
+        .env("XDG_CONFIG_HOME", home)
+        .env("NO_COLOR", "1")
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(stdout(&output), "8\n");
+}
+
+#[test]
+fn file_errors_go_to_stderr() {
+    for args in [["run", "missing.cb"], ["check", "missing.cb"]] {
+        let output = codebook(&args);
+        assert_eq!(output.status.code(), Some(1));
+        assert_eq!(stdout(&output), "");
+        assert!(String::from_utf8_lossy(&output.stderr).contains("File 'missing.cb' not found."));
+    }
+
+    let path = script("unclosed.cb", "{ 1 +");
+    let output = codebook(&["check", path.to_str().unwrap()]);
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(stdout(&output), "");
+    assert!(String::from_utf8_lossy(&output.stderr).contains("is never closed."));
+}